@@ -1,65 +1,302 @@
 use anchor_lang::prelude::*;
+use anchor_lang::{system_program, Discriminator};
+use anchor_lang::solana_program::{
+    ed25519_program,
+    instruction::{AccountMeta, Instruction},
+    program::{invoke, invoke_signed},
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{self, AssociatedToken},
     metadata::{
-        create_metadata_accounts_v3, create_mint_v2, create_master_edition_v3,
-        CreateMetadataAccountsV3, CreateMintV2, CreateMasterEditionV3,
-        Metadata, MetadataAccount, MasterEditionAccount,
+        create_master_edition_v3, create_metadata_accounts_v3, freeze_delegated_account,
+        verify_sized_collection_item, CreateMasterEditionV3, CreateMetadataAccountsV3,
+        FreezeDelegatedAccount, Metadata, VerifySizedCollectionItem,
+    },
+    token::{approve, burn, mint_to, Approve, Burn, Mint, MintTo, Token, TokenAccount},
+    token_2022::{
+        self, initialize_mint2,
+        spl_token_2022::{
+            extension::{metadata_pointer, ExtensionType},
+            instruction::{initialize_non_transferable_mint, AuthorityType},
+            state::Mint as Mint2022,
+        },
+        InitializeMint2, Token2022,
     },
-    token::{Mint, Token, TokenAccount},
 };
-use mpl_token_metadata::types::{DataV2, Creator, Collection, Uses, CollectionDetails};
+use mpl_token_metadata::types::{Collection, CollectionDetails, Creator, DataV2, TokenStandard, Uses};
+use spl_token_metadata_interface::{
+    self as token_metadata_interface,
+    state::{Field, TokenMetadata},
+};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
+/// Maximum number of challenges that can be tracked per user (one bit each in `challenges_completed`)
+pub const MAX_CHALLENGES: usize = 256;
+
+/// Number of bytes in the challenge bitmap
+pub const CHALLENGE_BITMAP_BYTES: usize = MAX_CHALLENGES / 8;
+
+/// Maximum number of modules that can be tracked per user (one bit each in `modules_completed`)
+pub const MAX_MODULES: usize = 8;
+
+/// RanchCoin is counted in whole coins, like the in-game economy
+pub const RANCH_COIN_DECIMALS: u8 = 0;
+
+/// Prefix of every challenge attestation message, so verifier signatures can't be reused for other messages
+pub const ATTESTATION_DOMAIN: &[u8] = b"shadow-ranch:challenge-attestation:v1";
+
+/// Daily streak lengths that emit a milestone event and can pay a bonus
+pub const STREAK_MILESTONES: [u16; 3] = [7, 30, 100];
+
+/// Length of a streak day, days start at midnight UTC
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Leaderboard id of the global XP leaderboard, other leaderboards use their module id
+pub const GLOBAL_LEADERBOARD: u8 = u8::MAX;
+
+/// Maximum number of entries a leaderboard can hold
+pub const MAX_LEADERBOARD_CAPACITY: u8 = 100;
+
+/// Positions on the ranch are percentages of its width and height
+pub const MAX_RANCH_POSITION: u16 = 100;
+
+/// Buildings can't be upgraded past this level
+pub const MAX_BUILDING_LEVEL: u8 = 10;
+
+/// Characters can't level up past this level
+pub const MAX_CHARACTER_LEVEL: u8 = 10;
+
+/// Maximum number of learning tracks (e.g. Solana Corps of Engineers, Cypherpunk Legacy)
+pub const MAX_TRACKS: usize = 8;
+
+/// Metaplex Bubblegum, which mints compressed achievements into a Merkle tree
+pub mod bubblegum {
+    use super::*;
+    
+    declare_id!("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+    
+    /// Anchor discriminator of Bubblegum's `create_tree` instruction
+    pub const CREATE_TREE_DISCRIMINATOR: [u8; 8] = [165, 83, 136, 142, 89, 202, 47, 220];
+    
+    /// Anchor discriminator of Bubblegum's `mint_to_collection_v1` instruction
+    pub const MINT_TO_COLLECTION_V1_DISCRIMINATOR: [u8; 8] = [153, 18, 178, 47, 197, 158, 86, 15];
+    
+    /// Offset of `num_minted` in `TreeConfig`:
+    /// discriminator (8) | tree_creator (32) | tree_delegate (32) | total_mint_capacity (8)
+    pub const TREE_CONFIG_NUM_MINTED_OFFSET: usize = 80;
+    
+    /// Asset id of the leaf minted into `merkle_tree` with the given nonce
+    pub fn asset_id(merkle_tree: &Pubkey, leaf_nonce: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"asset", merkle_tree.as_ref(), &leaf_nonce.to_le_bytes()],
+            &ID,
+        )
+        .0
+    }
+}
+
+/// SPL Account Compression, which owns the Merkle tree accounts
+pub mod spl_account_compression {
+    use super::*;
+    
+    declare_id!("cmtKgWPEyuAnGhCrNgFRQSMT9E4Dp4uErXqWs5rNo1D");
+}
+
+/// SPL Noop, used by Bubblegum to log leaf data for indexers
+pub mod spl_noop {
+    use super::*;
+    
+    declare_id!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+}
+
 #[program]
 pub mod shadow_ranch_program {
     use super::*;
 
+    /// Initialize the learner's progress account for one track
+    /// Completions are recorded per track when this account is passed to `complete_challenge` and `complete_module`
+    pub fn initialize_track(ctx: Context<InitializeTrack>, track_id: u8) -> Result<()> {
+        require!((track_id as usize) < MAX_TRACKS, ShadowRanchError::InvalidTrackId);
+        
+        // Sponsored learners have the account's rent charged against their budget
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            ctx.accounts.track_progress.to_account_info().lamports(),
+        )?;
+        
+        let track_progress = &mut ctx.accounts.track_progress;
+        
+        track_progress.version = TrackProgress::CURRENT_VERSION;
+        track_progress.authority = ctx.accounts.authority.key();
+        track_progress.track_id = track_id;
+        track_progress.challenges_completed = [0u8; CHALLENGE_BITMAP_BYTES];
+        track_progress.modules_completed = 0;
+        track_progress.bump = ctx.bumps.track_progress;
+        
+        let clock = Clock::get()?;
+        track_progress.created_at = clock.unix_timestamp;
+        track_progress.updated_at = clock.unix_timestamp;
+        
+        msg!("Track {} progress initialized for: {}", track_id, track_progress.authority);
+        Ok(())
+    }
+
     /// Initialize a new user progress account
     /// This creates a PDA account to track the user's learning progress
     pub fn initialize_user(ctx: Context<InitializeUser>) -> Result<()> {
+        // Sponsored learners have the account's rent charged against their budget
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            ctx.accounts.user_progress.to_account_info().lamports(),
+        )?;
+        
         let user_progress = &mut ctx.accounts.user_progress;
         
+        // New accounts always start on the current layout
+        user_progress.version = UserProgress::CURRENT_VERSION;
+        
         // Set the authority to the user who signed the transaction
         user_progress.authority = ctx.accounts.authority.key();
         
-        // Initialize challenges completed to 0 (no challenges completed yet)
-        user_progress.challenges_completed = 0;
+        // Initialize the challenge bitmap to all zeros (no challenges completed yet)
+        user_progress.challenges_completed = [0u8; CHALLENGE_BITMAP_BYTES];
         
         // Initialize module progress to 0
         user_progress.modules_completed = 0;
         
+        // No module has been started yet
+        user_progress.module_started_at = [0; MAX_MODULES];
+        
+        // Everyone starts at level 1 without any XP
+        user_progress.xp = 0;
+        user_progress.level = 1;
+        
+        // Rent paid by a sponsor goes back to them when the account is closed
+        user_progress.sponsored_by = if ctx.accounts.sponsor.is_some() {
+            ctx.accounts.payer.key()
+        } else {
+            Pubkey::default()
+        };
+        
+        // A learner coming back after `close_user` keeps their minted achievements and claimed rewards,
+        // so closing and reopening can't be used to farm them again
+        // The tombstone's address is a PDA of this program, so only `close_user` can have written it
+        let tombstone_info = &ctx.accounts.tombstone;
+        if !tombstone_info.data_is_empty() {
+            let tombstone = UserTombstone::try_deserialize(&mut &tombstone_info.try_borrow_data()?[..])?;
+            user_progress.minted_modules = tombstone.minted_modules;
+            user_progress.rewards_claimed = tombstone.rewards_claimed;
+            user_progress.streak_bonuses_claimed = tombstone.streak_bonuses_claimed;
+        }
+        
         // Set timestamps
         let clock = Clock::get()?;
         user_progress.created_at = clock.unix_timestamp;
         user_progress.updated_at = clock.unix_timestamp;
         
+        emit!(UserInitialized {
+            authority: user_progress.authority,
+            timestamp: clock.unix_timestamp,
+        });
+        
         msg!("User progress account initialized for: {}", ctx.accounts.authority.key());
         Ok(())
     }
 
     /// Complete a specific challenge
-    /// This updates the bitmask to mark a challenge as completed
-    pub fn complete_challenge(ctx: Context<CompleteChallenge>, challenge_id: u8) -> Result<()> {
-        require!(challenge_id < 16, ShadowRanchError::InvalidChallengeId);
+    /// Either the verifier signs directly, or the learner signs and the preceding instruction is an
+    /// Ed25519 signature check by the verifier over `challenge_attestation_message`
+    pub fn complete_challenge(ctx: Context<CompleteChallenge>, challenge_id: u8, expiry: i64) -> Result<()> {
+        require!((challenge_id as usize) < MAX_CHALLENGES, ShadowRanchError::InvalidChallengeId);
         
         let user_progress = &mut ctx.accounts.user_progress;
+        let signer = ctx.accounts.authority.key();
         
-        // Verify the signer is the authority of this progress account
+        // Only the learner or the verifier may submit the completion
         require!(
-            ctx.accounts.authority.key() == user_progress.authority,
+            signer == user_progress.authority || signer == ctx.accounts.verifier.grantee,
             ShadowRanchError::Unauthorized
         );
         
-        // Use bitwise OR to mark the challenge as completed
-        // challenge_id 0 corresponds to bit 0, challenge_id 1 to bit 1, etc.
-        let challenge_mask = 1u16 << challenge_id;
-        user_progress.challenges_completed |= challenge_mask;
-        
-        // Update timestamp
+        // Reject stale attestations
         let clock = Clock::get()?;
-        user_progress.updated_at = clock.unix_timestamp;
+        require!(clock.unix_timestamp <= expiry, ShadowRanchError::AttestationExpired);
+        
+        // Check the verifier signed exactly this completion, unless they signed the transaction
+        // An attestation only completes a challenge that isn't completed yet, so it can't be replayed,
+        // while attestations for different challenges can land in any order
+        if signer != ctx.accounts.verifier.grantee {
+            require!(
+                !user_progress.is_challenge_completed(challenge_id),
+                ShadowRanchError::AttestationReplayed
+            );
+            
+            let message = challenge_attestation_message(&user_progress.authority, challenge_id, expiry);
+            let instructions = ctx.accounts.instructions.to_account_info();
+            let current_index = load_current_index_checked(&instructions)?;
+            require!(current_index > 0, ShadowRanchError::InvalidAttestation);
+            let ed25519_ix = load_instruction_at_checked((current_index - 1) as usize, &instructions)?;
+            verify_ed25519_instruction(&ed25519_ix, &ctx.accounts.verifier.grantee, &message)?;
+        }
+        
+        // In strict mode, challenges of modules whose prerequisites aren't complete stay locked
+        if ctx.accounts.curriculum.strict_prerequisites {
+            if let Some(module) = ctx.accounts.curriculum.module_of_challenge(challenge_id) {
+                require!(
+                    user_progress.has_completed_prerequisites(module),
+                    ShadowRanchError::PrerequisiteNotMet
+                );
+            }
+        }
+        
+        // Set the challenge's bit in the bitmap
+        // challenge_id 0 corresponds to bit 0 of byte 0, challenge_id 8 to bit 0 of byte 1, etc.
+        let first_completion = !user_progress.is_challenge_completed(challenge_id);
+        user_progress.set_challenge_completed(challenge_id);
+        
+        // Award XP and update timestamp and daily streak the first time only,
+        // so repeating a completed challenge doesn't count as activity
+        if first_completion {
+            let module = ctx.accounts.curriculum.module_of_challenge(challenge_id);
+            
+            // The first completed challenge of a module starts the clock for its leaderboard
+            if let Some(module) = module {
+                let started_at = &mut user_progress.module_started_at[module.module_id as usize];
+                if *started_at == 0 {
+                    *started_at = clock.unix_timestamp;
+                }
+            }
+            
+            let xp = module.map_or(0, |module| module.challenge_xp as u64);
+            user_progress.award_xp(xp, &ctx.accounts.curriculum);
+            user_progress.updated_at = clock.unix_timestamp;
+            record_streak(user_progress, clock.unix_timestamp);
+        } else {
+            // A repeat still picks up XP curve changes
+            user_progress.refresh_level(&ctx.accounts.curriculum);
+        }
+        
+        // Also record it in the learner's progress for the challenge's track, if they passed it
+        if let Some(track_progress) = ctx.accounts.track_progress.as_mut() {
+            let module = ctx.accounts.curriculum
+                .module_of_challenge(challenge_id)
+                .ok_or(ShadowRanchError::InvalidChallengeId)?;
+            require!(module.track_id == track_progress.track_id, ShadowRanchError::InvalidTrackId);
+            
+            track_progress.set_challenge_completed(challenge_id);
+            track_progress.updated_at = clock.unix_timestamp;
+        }
+        
+        emit!(ChallengeCompleted {
+            authority: user_progress.authority,
+            challenge_id,
+            challenges_completed: user_progress.challenges_completed,
+            xp: user_progress.xp,
+            level: user_progress.level,
+            timestamp: clock.unix_timestamp,
+        });
         
         msg!("Challenge {} completed for user: {}", challenge_id, user_progress.authority);
         Ok(())
@@ -68,55 +305,110 @@ pub mod shadow_ranch_program {
     /// Complete a module (requires completing all challenges in the module)
     /// This is called when a user finishes all challenges in a learning module
     pub fn complete_module(ctx: Context<CompleteModule>, module_id: u8) -> Result<()> {
-        require!(module_id < 4, ShadowRanchError::InvalidModuleId);
-        
         let user_progress = &mut ctx.accounts.user_progress;
         
-        // Verify the signer is the authority of this progress account
+        // Look up the module's challenges in the on-chain curriculum
+        let module = ctx.accounts.curriculum
+            .find_module(module_id)
+            .ok_or(ShadowRanchError::InvalidModuleId)?;
+        
+        // The learner, a verifier or the module's instructor can complete it
+        require_learner_or_role(
+            &ctx.accounts.authority.key(),
+            &user_progress.authority,
+            ctx.accounts.role_grant.as_deref(),
+            &[Role::Instructor, Role::Verifier],
+            module.track_id,
+        )?;
+        
+        // The module's prerequisites must be completed first
         require!(
-            ctx.accounts.authority.key() == user_progress.authority,
-            ShadowRanchError::Unauthorized
+            user_progress.has_completed_prerequisites(module),
+            ShadowRanchError::PrerequisiteNotMet
         );
         
         // Check if all challenges for this module are completed
-        let module_challenges = get_module_challenges(module_id);
-        let module_mask = create_module_mask(module_challenges);
-        
         require!(
-            (user_progress.challenges_completed & module_mask) == module_mask,
+            module.challenge_ids
+                .iter()
+                .all(|&challenge_id| user_progress.is_challenge_completed(challenge_id)),
             ShadowRanchError::ModuleNotComplete
         );
         
         // Mark module as completed using bitwise OR
         let module_completion_mask = 1u8 << module_id;
+        let first_completion = (user_progress.modules_completed & module_completion_mask) == 0;
         user_progress.modules_completed |= module_completion_mask;
         
-        // Update timestamp
+        // Award XP and update timestamp and daily streak the first time only,
+        // so repeating a completed module doesn't count as activity
         let clock = Clock::get()?;
-        user_progress.updated_at = clock.unix_timestamp;
+        if first_completion {
+            user_progress.award_xp(module.module_xp as u64, &ctx.accounts.curriculum);
+            user_progress.updated_at = clock.unix_timestamp;
+            record_streak(user_progress, clock.unix_timestamp);
+        } else {
+            // A repeat still picks up XP curve changes
+            user_progress.refresh_level(&ctx.accounts.curriculum);
+        }
+        
+        // Also record it in the learner's progress for the module's track, if they passed it
+        if let Some(track_progress) = ctx.accounts.track_progress.as_mut() {
+            require!(module.track_id == track_progress.track_id, ShadowRanchError::InvalidTrackId);
+            
+            track_progress.modules_completed |= module_completion_mask;
+            track_progress.updated_at = clock.unix_timestamp;
+        }
+        
+        // Rank the learner by XP, and by how long they took to get through this module
+        // Only first completions are ranked, so a repeat can't push the learner past others with the same score
+        require_leaderboard(&ctx.accounts.config, GLOBAL_LEADERBOARD, ctx.accounts.global_leaderboard.as_deref())?;
+        require_leaderboard(&ctx.accounts.config, module_id, ctx.accounts.module_leaderboard.as_deref())?;
+        if first_completion {
+            if let Some(leaderboard) = ctx.accounts.global_leaderboard.as_mut() {
+                leaderboard.submit(user_progress.authority, user_progress.xp, clock.unix_timestamp);
+            }
+            if let Some(leaderboard) = ctx.accounts.module_leaderboard.as_mut() {
+                // Modules started before the account was migrated have no start time and aren't timed
+                let started_at = user_progress.module_started_at[module_id as usize];
+                if started_at != 0 {
+                    let completion_time = clock.unix_timestamp.saturating_sub(started_at).max(0);
+                    leaderboard.submit_best(user_progress.authority, completion_time as u64, clock.unix_timestamp);
+                }
+            }
+        }
+        
+        emit!(ModuleCompleted {
+            authority: user_progress.authority,
+            module_id,
+            modules_completed: user_progress.modules_completed,
+            xp: user_progress.xp,
+            level: user_progress.level,
+            timestamp: clock.unix_timestamp,
+        });
         
         msg!("Module {} completed for user: {}", module_id, user_progress.authority);
         Ok(())
     }
 
     /// Mint an achievement NFT for completing a module
-    /// This performs a CPI to the Metaplex Token Metadata program
-    pub fn mint_achievement_nft(
-        ctx: Context<MintAchievementNft>,
-        title: String,
-        symbol: String,
-        uri: String,
-        module_id: u8,
-    ) -> Result<()> {
-        require!(module_id < 4, ShadowRanchError::InvalidModuleId);
+    /// This performs a CPI to the Metaplex Token Metadata program, using the module's template metadata
+    pub fn mint_achievement_nft(ctx: Context<MintAchievementNft>, module_id: u8) -> Result<()> {
+        require!((module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
         
-        let user_progress = &ctx.accounts.user_progress;
+        // Measured before any CPI, so sponsored mints can be charged what the payer spent
+        let payer_lamports = ctx.accounts.payer.lamports();
         
-        // Verify the signer is the authority of this progress account
-        require!(
-            ctx.accounts.authority.key() == user_progress.authority,
-            ShadowRanchError::Unauthorized
-        );
+        let user_progress = &mut ctx.accounts.user_progress;
+        
+        // The learner or an instructor of the module's track can mint
+        require_learner_or_role(
+            &ctx.accounts.authority.key(),
+            &user_progress.authority,
+            ctx.accounts.role_grant.as_deref(),
+            &[Role::Instructor],
+            ctx.accounts.curriculum.track_of(module_id),
+        )?;
         
         // Check if the module is completed before allowing NFT mint
         let module_completion_mask = 1u8 << module_id;
@@ -125,263 +417,4608 @@ pub mod shadow_ranch_program {
             ShadowRanchError::ModuleNotComplete
         );
         
-        // Create the mint account
-        let cpi_accounts = CreateMintV2 {
+        // Only one achievement NFT per module
+        require!(
+            (user_progress.minted_modules & module_completion_mask) == 0,
+            ShadowRanchError::AchievementAlreadyMinted
+        );
+        user_progress.minted_modules |= module_completion_mask;
+        
+        charge_mint_fee(
+            &ctx.accounts.config,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        
+        // The achievement authority PDA holds mint, freeze and update authority over every
+        // achievement, so only the program can issue or change them
+        let achievement_authority_seeds: &[&[&[u8]]] = &[&[
+            b"achievement_authority",
+            &[ctx.bumps.achievement_authority],
+        ]];
+        
+        // Mint 1 token to the user's associated token account
+        let cpi_accounts = MintTo {
             mint: ctx.accounts.mint.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
-            payer: ctx.accounts.payer.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info(),
-            token_program: ctx.accounts.token_program.to_account_info(),
-            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
-            rent: ctx.accounts.rent.to_account_info(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.achievement_authority.to_account_info(),
         };
         
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        create_mint_v2(cpi_ctx, 0, &ctx.accounts.authority.key(), None, None)?;
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        mint_to(cpi_ctx, 1)?;
         
-        // Create the metadata account
+        // Create the metadata account, with the program as the sole verified creator
         let creators = vec![
             Creator {
-                address: ctx.accounts.authority.key(),
+                address: ctx.accounts.achievement_authority.key(),
                 verified: true,
                 share: 100,
             }
         ];
         
+        let template = &ctx.accounts.template;
         let data_v2 = DataV2 {
-            name: title,
-            symbol,
-            uri,
+            name: template.name.clone(),
+            symbol: template.symbol.clone(),
+            uri: template.uri.clone(),
             seller_fee_basis_points: 0, // No royalties for educational achievements
             creators: Some(creators),
-            collection: None,
+            collection: Some(Collection {
+                verified: false, // Verified below via CPI
+                key: ctx.accounts.collection_mint.key(),
+            }),
             uses: None,
         };
         
         let cpi_accounts = CreateMetadataAccountsV3 {
             metadata: ctx.accounts.metadata.to_account_info(),
             mint: ctx.accounts.mint.to_account_info(),
-            mint_authority: ctx.accounts.authority.to_account_info(),
+            mint_authority: ctx.accounts.achievement_authority.to_account_info(),
             payer: ctx.accounts.payer.to_account_info(),
-            update_authority: ctx.accounts.authority.to_account_info(),
+            update_authority: ctx.accounts.achievement_authority.to_account_info(),
             system_program: ctx.accounts.system_program.to_account_info(),
-            rent: Some(ctx.accounts.rent.key()),
+            rent: ctx.accounts.rent.to_account_info(),
         };
         
-        let cpi_ctx = CpiContext::new(ctx.accounts.metadata_program.to_account_info(), cpi_accounts);
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
         create_metadata_accounts_v3(
             cpi_ctx,
             data_v2,
             true, // is_mutable
             true, // update_authority_is_signer
             None, // collection_details
-            None, // uses
         )?;
         
         // Create the master edition account (non-fungible)
+        // This moves mint authority to the edition, so no further tokens can be minted
         let cpi_accounts = CreateMasterEditionV3 {
             edition: ctx.accounts.master_edition.to_account_info(),
             mint: ctx.accounts.mint.to_account_info(),
-            update_authority: ctx.accounts.authority.to_account_info(),
-            mint_authority: ctx.accounts.authority.to_account_info(),
+            update_authority: ctx.accounts.achievement_authority.to_account_info(),
+            mint_authority: ctx.accounts.achievement_authority.to_account_info(),
             payer: ctx.accounts.payer.to_account_info(),
             metadata: ctx.accounts.metadata.to_account_info(),
             token_program: ctx.accounts.token_program.to_account_info(),
             system_program: ctx.accounts.system_program.to_account_info(),
-            rent: Some(ctx.accounts.rent.key()),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        create_master_edition_v3(cpi_ctx, Some(0))?;
+        
+        // Verify the NFT as a member of its track's collection
+        let cpi_accounts = VerifySizedCollectionItem {
+            payer: ctx.accounts.payer.to_account_info(),
+            metadata: ctx.accounts.metadata.to_account_info(),
+            collection_authority: ctx.accounts.achievement_authority.to_account_info(),
+            collection_mint: ctx.accounts.collection_mint.to_account_info(),
+            collection_metadata: ctx.accounts.collection_metadata.to_account_info(),
+            collection_master_edition: ctx.accounts.collection_master_edition.to_account_info(),
+        };
+        
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        verify_sized_collection_item(cpi_ctx, None)?;
+        
+        // Soulbound achievements are frozen in the learner's wallet
+        // The program is made delegate first, since only a delegate can freeze once the
+        // master edition holds the mint's freeze authority
+        // Approving needs the learner's signature, even when an instructor mints for them
+        if ctx.accounts.template.soulbound {
+            require!(ctx.accounts.learner.is_signer, ShadowRanchError::Unauthorized);
+            
+            let cpi_accounts = Approve {
+                to: ctx.accounts.user_token_account.to_account_info(),
+                delegate: ctx.accounts.achievement_authority.to_account_info(),
+                authority: ctx.accounts.learner.to_account_info(),
+            };
+            
+            let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+            approve(cpi_ctx, 1)?;
+            
+            let cpi_accounts = FreezeDelegatedAccount {
+                metadata: ctx.accounts.metadata.to_account_info(),
+                delegate: ctx.accounts.achievement_authority.to_account_info(),
+                token_account: ctx.accounts.user_token_account.to_account_info(),
+                edition: ctx.accounts.master_edition.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+            };
+            
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.metadata_program.to_account_info(),
+                cpi_accounts,
+                achievement_authority_seeds,
+            );
+            freeze_delegated_account(cpi_ctx)?;
+        }
+        
+        // Sponsored mints have everything the payer spent charged against the learner's budget
+        let spent = payer_lamports - ctx.accounts.payer.lamports()
+            + ctx.accounts.mint.to_account_info().lamports()
+            + ctx.accounts.user_token_account.to_account_info().lamports();
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            spent,
+        )?;
+        
+        emit!(AchievementMinted {
+            authority: ctx.accounts.user_progress.authority,
+            module_id,
+            asset: ctx.accounts.mint.key(),
+            minted_modules: ctx.accounts.user_progress.minted_modules,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Achievement NFT minted for module {} completion!", module_id);
+        Ok(())
+    }
+
+    /// Mint an achievement for completing a module as a Token-2022 NFT
+    /// Metadata lives on the mint itself and the NonTransferable extension makes it soulbound, without Metaplex
+    pub fn mint_achievement_nft_2022(ctx: Context<MintAchievementNft2022>, module_id: u8) -> Result<()> {
+        require!((module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
+        
+        // Measured before any CPI, so sponsored mints can be charged what the payer spent
+        let payer_lamports = ctx.accounts.payer.lamports();
+        
+        let user_progress = &mut ctx.accounts.user_progress;
+        
+        // The learner or an instructor of the module's track can mint
+        require_learner_or_role(
+            &ctx.accounts.authority.key(),
+            &user_progress.authority,
+            ctx.accounts.role_grant.as_deref(),
+            &[Role::Instructor],
+            ctx.accounts.curriculum.track_of(module_id),
+        )?;
+        
+        // Check if the module is completed before allowing NFT mint
+        let module_completion_mask = 1u8 << module_id;
+        require!(
+            (user_progress.modules_completed & module_completion_mask) != 0,
+            ShadowRanchError::ModuleNotComplete
+        );
+        
+        // Only one achievement per module, whichever token program it was minted with
+        require!(
+            (user_progress.minted_modules & module_completion_mask) == 0,
+            ShadowRanchError::AchievementAlreadyMinted
+        );
+        user_progress.minted_modules |= module_completion_mask;
+        
+        charge_mint_fee(
+            &ctx.accounts.config,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        
+        let achievement_authority_seeds: &[&[&[u8]]] = &[&[
+            b"achievement_authority",
+            &[ctx.bumps.achievement_authority],
+        ]];
+        let learner_key = ctx.accounts.learner.key();
+        let mint_seeds: &[&[&[u8]]] = &[&[
+            b"achievement_mint_2022",
+            learner_key.as_ref(),
+            &[module_id],
+            &[ctx.bumps.mint],
+        ]];
+        
+        let token_program_id = ctx.accounts.token_program.key();
+        let mint_key = ctx.accounts.mint.key();
+        let achievement_authority_key = ctx.accounts.achievement_authority.key();
+        let mint_info = ctx.accounts.mint.to_account_info();
+        let achievement_authority_info = ctx.accounts.achievement_authority.to_account_info();
+        
+        // The template's attributes are stored as additional metadata fields
+        let template = &ctx.accounts.template;
+        let token_metadata = TokenMetadata {
+            mint: mint_key,
+            name: template.name.clone(),
+            symbol: template.symbol.clone(),
+            uri: template.uri.clone(),
+            additional_metadata: template
+                .attributes
+                .iter()
+                .map(|attribute| (attribute.trait_type.clone(), attribute.value.clone()))
+                .collect(),
+            ..Default::default()
         };
         
-        let cpi_ctx = CpiContext::new(ctx.accounts.metadata_program.to_account_info(), cpi_accounts);
-        create_master_edition_v3(cpi_ctx, None)?;
+        // Allocate only the fixed-size extensions, Token-2022 reallocs the mint as metadata is written
+        // Rent for the metadata is paid upfront so every realloc stays rent exempt
+        let mint_space = ExtensionType::try_calculate_account_len::<Mint2022>(&[
+            ExtensionType::NonTransferable,
+            ExtensionType::MetadataPointer,
+        ])?;
+        let lamports = Rent::get()?.minimum_balance(mint_space + token_metadata.tlv_size_of()?);
+        create_pda_account(
+            &ctx.accounts.payer.to_account_info(),
+            &mint_info,
+            &ctx.accounts.system_program.to_account_info(),
+            lamports,
+            mint_space,
+            &token_program_id,
+            mint_seeds,
+        )?;
+        
+        // Extensions must be initialized before the mint itself
+        invoke(
+            &initialize_non_transferable_mint(&token_program_id, &mint_key)?,
+            std::slice::from_ref(&mint_info),
+        )?;
+        invoke(
+            &metadata_pointer::instruction::initialize(
+                &token_program_id,
+                &mint_key,
+                Some(achievement_authority_key),
+                Some(mint_key),
+            )?,
+            std::slice::from_ref(&mint_info),
+        )?;
+        
+        let cpi_accounts = InitializeMint2 { mint: mint_info.clone() };
+        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
+        initialize_mint2(
+            cpi_ctx,
+            0,
+            &achievement_authority_key,
+            Some(&achievement_authority_key),
+        )?;
+        
+        // Write the template metadata into the mint, with the program as update authority
+        invoke_signed(
+            &token_metadata_interface::instruction::initialize(
+                &token_program_id,
+                &mint_key,
+                &achievement_authority_key,
+                &mint_key,
+                &achievement_authority_key,
+                token_metadata.name,
+                token_metadata.symbol,
+                token_metadata.uri,
+            ),
+            &[mint_info.clone(), achievement_authority_info.clone()],
+            achievement_authority_seeds,
+        )?;
+        for (key, value) in token_metadata.additional_metadata {
+            invoke_signed(
+                &token_metadata_interface::instruction::update_field(
+                    &token_program_id,
+                    &mint_key,
+                    &achievement_authority_key,
+                    Field::Key(key),
+                    value,
+                ),
+                &[mint_info.clone(), achievement_authority_info.clone()],
+                achievement_authority_seeds,
+            )?;
+        }
         
-        // Create the user's token account
-        let cpi_accounts = AssociatedToken::create(
+        // Create the learner's Token-2022 associated token account and mint 1 token to it
+        let cpi_accounts = associated_token::Create {
+            payer: ctx.accounts.payer.to_account_info(),
+            associated_token: ctx.accounts.user_token_account.to_account_info(),
+            authority: ctx.accounts.learner.to_account_info(),
+            mint: mint_info.clone(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(
             ctx.accounts.associated_token_program.to_account_info(),
-            ctx.accounts.authority.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
-            ctx.accounts.authority.to_account_info(),
+            cpi_accounts,
+        );
+        associated_token::create(cpi_ctx)?;
+        
+        let cpi_accounts = token_2022::MintTo {
+            mint: mint_info.clone(),
+            to: ctx.accounts.user_token_account.to_account_info(),
+            authority: achievement_authority_info.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            ctx.accounts.system_program.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        token_2022::mint_to(cpi_ctx, 1)?;
+        
+        // Drop the mint authority so the supply is fixed at 1
+        let cpi_accounts = token_2022::SetAuthority {
+            current_authority: achievement_authority_info,
+            account_or_mint: mint_info,
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
         );
+        token_2022::set_authority(cpi_ctx, AuthorityType::MintTokens, None)?;
         
-        cpi_accounts?;
+        // Sponsored mints have everything the payer spent charged against the learner's budget
+        let spent = payer_lamports - ctx.accounts.payer.lamports();
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            spent,
+        )?;
         
-        // Mint 1 token to the user's account
-        let cpi_accounts = anchor_spl::token::MintTo {
-            mint: ctx.accounts.mint.to_account_info(),
-            to: ctx.accounts.user_token_account.to_account_info(),
-            authority: ctx.accounts.authority.to_account_info(),
+        emit!(AchievementMinted {
+            authority: ctx.accounts.user_progress.authority,
+            module_id,
+            asset: ctx.accounts.mint.key(),
+            minted_modules: ctx.accounts.user_progress.minted_modules,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Token-2022 achievement minted for module {} completion!", module_id);
+        Ok(())
+    }
+
+    /// Mint an achievement for completing a module as a compressed NFT
+    /// The leaf is appended to the admin's Merkle tree via a CPI to Bubblegum, so no accounts are created per learner
+    pub fn mint_compressed_achievement(
+        ctx: Context<MintCompressedAchievement>,
+        module_id: u8,
+    ) -> Result<()> {
+        require!((module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
+        
+        // Measured before any CPI, so sponsored mints can be charged what the payer spent
+        let payer_lamports = ctx.accounts.payer.lamports();
+        
+        let user_progress = &mut ctx.accounts.user_progress;
+        
+        // The learner or an instructor of the module's track can mint
+        require_learner_or_role(
+            &ctx.accounts.authority.key(),
+            &user_progress.authority,
+            ctx.accounts.role_grant.as_deref(),
+            &[Role::Instructor],
+            ctx.accounts.curriculum.track_of(module_id),
+        )?;
+        
+        // Check if the module is completed before allowing NFT mint
+        let module_completion_mask = 1u8 << module_id;
+        require!(
+            (user_progress.modules_completed & module_completion_mask) != 0,
+            ShadowRanchError::ModuleNotComplete
+        );
+        
+        // Only one achievement per module, whichever way it was minted
+        require!(
+            (user_progress.minted_modules & module_completion_mask) == 0,
+            ShadowRanchError::AchievementAlreadyMinted
+        );
+        
+        // Compressed NFTs can't be frozen, so soulbound achievements must use the other mint paths
+        let template = &ctx.accounts.template;
+        require!(!template.soulbound, ShadowRanchError::SoulboundNotSupported);
+        
+        user_progress.minted_modules |= module_completion_mask;
+        
+        charge_mint_fee(
+            &ctx.accounts.config,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        
+        let metadata = CompressedMetadataArgs {
+            name: template.name.clone(),
+            symbol: template.symbol.clone(),
+            uri: template.uri.clone(),
+            seller_fee_basis_points: 0, // No royalties for educational achievements
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: Some(TokenStandard::NonFungible),
+            collection: Some(Collection {
+                verified: false, // Verified by Bubblegum, since the program is the collection authority
+                key: ctx.accounts.collection_mint.key(),
+            }),
+            uses: None,
+            token_program_version: 0, // TokenProgramVersion::Original
+            creators: vec![Creator {
+                address: ctx.accounts.achievement_authority.key(),
+                verified: true,
+                share: 100,
+            }],
         };
+        let mut data = bubblegum::MINT_TO_COLLECTION_V1_DISCRIMINATOR.to_vec();
+        metadata.serialize(&mut data)?;
         
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        anchor_spl::token::mint_to(cpi_ctx, 1)?;
+        // Bubblegum uses the tree's mint count as the new leaf's nonce, which the asset id is derived from
+        let leaf_nonce = {
+            let tree_config = ctx.accounts.tree_config.try_borrow_data()?;
+            let at = bubblegum::TREE_CONFIG_NUM_MINTED_OFFSET;
+            let num_minted = tree_config
+                .get(at..at + 8)
+                .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+            u64::from_le_bytes(num_minted.try_into().unwrap())
+        };
         
-        msg!("Achievement NFT minted for module {} completion!", module_id);
+        // The achievement authority is both the tree delegate and the collection authority
+        let accounts = vec![
+            AccountMeta::new(ctx.accounts.tree_config.key(), false),
+            AccountMeta::new_readonly(ctx.accounts.learner.key(), false), // leaf owner
+            AccountMeta::new_readonly(ctx.accounts.learner.key(), false), // leaf delegate
+            AccountMeta::new(ctx.accounts.merkle_tree.key(), false),
+            AccountMeta::new_readonly(ctx.accounts.payer.key(), true),
+            AccountMeta::new_readonly(ctx.accounts.achievement_authority.key(), true), // tree delegate
+            AccountMeta::new_readonly(ctx.accounts.achievement_authority.key(), true), // collection authority
+            AccountMeta::new_readonly(bubblegum::ID, false), // no collection authority record
+            AccountMeta::new_readonly(ctx.accounts.collection_mint.key(), false),
+            AccountMeta::new(ctx.accounts.collection_metadata.key(), false),
+            AccountMeta::new_readonly(ctx.accounts.collection_master_edition.key(), false),
+            AccountMeta::new_readonly(ctx.accounts.bubblegum_signer.key(), false),
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(ctx.accounts.metadata_program.key(), false),
+            AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
+        ];
+        let account_infos = [
+            ctx.accounts.tree_config.to_account_info(),
+            ctx.accounts.learner.to_account_info(),
+            ctx.accounts.merkle_tree.to_account_info(),
+            ctx.accounts.payer.to_account_info(),
+            ctx.accounts.achievement_authority.to_account_info(),
+            ctx.accounts.bubblegum_program.to_account_info(),
+            ctx.accounts.collection_mint.to_account_info(),
+            ctx.accounts.collection_metadata.to_account_info(),
+            ctx.accounts.collection_master_edition.to_account_info(),
+            ctx.accounts.bubblegum_signer.to_account_info(),
+            ctx.accounts.log_wrapper.to_account_info(),
+            ctx.accounts.compression_program.to_account_info(),
+            ctx.accounts.metadata_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ];
+        invoke_signed(
+            &Instruction {
+                program_id: bubblegum::ID,
+                accounts,
+                data,
+            },
+            &account_infos,
+            &[&[b"achievement_authority", &[ctx.bumps.achievement_authority]]],
+        )?;
+        
+        ctx.accounts.achievement_tree.minted += 1;
+        
+        // Sponsored mints have everything the payer spent charged against the learner's budget
+        let spent = payer_lamports - ctx.accounts.payer.lamports();
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            spent,
+        )?;
+        
+        emit!(AchievementMinted {
+            authority: ctx.accounts.user_progress.authority,
+            module_id,
+            asset: bubblegum::asset_id(&ctx.accounts.merkle_tree.key(), leaf_nonce),
+            minted_modules: ctx.accounts.user_progress.minted_modules,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        
+        msg!("Compressed achievement minted for module {} completion!", module_id);
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-pub struct InitializeUser<'info> {
-    #[account(
-        init,
-        payer = payer,
-        space = UserProgress::LEN,
-        seeds = [b"user_progress", authority.key().as_ref()],
-        bump
-    )]
-    pub user_progress: Account<'info, UserProgress>,
-    
-    #[account(mut)]
-    pub payer: Signer<'info>,
-    
-    /// CHECK: This is the user's authority key
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Revoke a fraudulent challenge completion
+    /// Only the program admin or a moderator can revoke, recording a reason code in the emitted event
+    pub fn revoke_challenge(ctx: Context<RevokeProgress>, challenge_id: u8, reason: u8) -> Result<()> {
+        require_admin_or_moderator(
+            &ctx.accounts.config,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.role_grant.as_deref(),
+        )?;
+        
+        let user_progress = &mut ctx.accounts.user_progress;
+        require!(
+            user_progress.is_challenge_completed(challenge_id),
+            ShadowRanchError::ChallengeNotCompleted
+        );
+        user_progress.clear_challenge_completed(challenge_id);
+        
+        // Take back the XP the challenge awarded
+        let xp = ctx.accounts.curriculum
+            .module_of_challenge(challenge_id)
+            .map_or(0, |module| module.challenge_xp as u64);
+        user_progress.revoke_xp(xp, &ctx.accounts.curriculum);
+        
+        let clock = Clock::get()?;
+        user_progress.updated_at = clock.unix_timestamp;
+        
+        // Also clear it from the learner's track progress, if they passed it
+        if let Some(track_progress) = ctx.accounts.track_progress.as_mut() {
+            let module = ctx.accounts.curriculum
+                .module_of_challenge(challenge_id)
+                .ok_or(ShadowRanchError::InvalidChallengeId)?;
+            require!(module.track_id == track_progress.track_id, ShadowRanchError::InvalidTrackId);
+            
+            track_progress.clear_challenge_completed(challenge_id);
+            track_progress.updated_at = clock.unix_timestamp;
+        }
+        
+        // Rank the learner by what's left of their XP
+        require_leaderboard(&ctx.accounts.config, GLOBAL_LEADERBOARD, ctx.accounts.global_leaderboard.as_deref())?;
+        if let Some(leaderboard) = ctx.accounts.global_leaderboard.as_mut() {
+            leaderboard.resubmit(user_progress.authority, user_progress.xp, clock.unix_timestamp);
+        }
+        
+        emit!(ChallengeRevoked {
+            learner: user_progress.authority,
+            challenge_id,
+            reason,
+            revoked_by: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        
+        msg!("Challenge {} revoked for user: {}", challenge_id, user_progress.authority);
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct CompleteChallenge<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_progress", authority.key().as_ref()],
-        bump,
-        has_one = authority
+    /// Revoke a fraudulent module completion
+    /// Only the program admin or a moderator can revoke, recording a reason code in the emitted event
+    pub fn revoke_module(ctx: Context<RevokeProgress>, module_id: u8, reason: u8) -> Result<()> {
+        require!((module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
+        
+        require_admin_or_moderator(
+            &ctx.accounts.config,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.role_grant.as_deref(),
+        )?;
+        
+        let user_progress = &mut ctx.accounts.user_progress;
+        let module_completion_mask = 1u8 << module_id;
+        require!(
+            (user_progress.modules_completed & module_completion_mask) != 0,
+            ShadowRanchError::ModuleNotComplete
+        );
+        user_progress.modules_completed &= !module_completion_mask;
+        
+        // Take back the XP the module awarded
+        let xp = ctx.accounts.curriculum
+            .find_module(module_id)
+            .map_or(0, |module| module.module_xp as u64);
+        user_progress.revoke_xp(xp, &ctx.accounts.curriculum);
+        
+        let clock = Clock::get()?;
+        user_progress.updated_at = clock.unix_timestamp;
+        
+        // Also clear it from the learner's track progress, if they passed it
+        if let Some(track_progress) = ctx.accounts.track_progress.as_mut() {
+            let module = ctx.accounts.curriculum
+                .find_module(module_id)
+                .ok_or(ShadowRanchError::InvalidModuleId)?;
+            require!(module.track_id == track_progress.track_id, ShadowRanchError::InvalidTrackId);
+            
+            track_progress.modules_completed &= !module_completion_mask;
+            track_progress.updated_at = clock.unix_timestamp;
+        }
+        
+        // Rank the learner by what's left of their XP, and drop their time for the module
+        require_leaderboard(&ctx.accounts.config, GLOBAL_LEADERBOARD, ctx.accounts.global_leaderboard.as_deref())?;
+        require_leaderboard(&ctx.accounts.config, module_id, ctx.accounts.module_leaderboard.as_deref())?;
+        if let Some(leaderboard) = ctx.accounts.global_leaderboard.as_mut() {
+            leaderboard.resubmit(user_progress.authority, user_progress.xp, clock.unix_timestamp);
+        }
+        if let Some(leaderboard) = ctx.accounts.module_leaderboard.as_mut() {
+            require!(leaderboard.leaderboard_id == module_id, ShadowRanchError::InvalidModuleId);
+            leaderboard.remove(&user_progress.authority);
+        }
+        
+        emit!(ModuleRevoked {
+            learner: user_progress.authority,
+            module_id,
+            reason,
+            revoked_by: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        
+        msg!("Module {} revoked for user: {}", module_id, user_progress.authority);
+        Ok(())
+    }
+
+    /// Revoke a minted achievement by recording a revocation receipt for it
+    /// The achievement stays marked as minted, so it can't be minted again once revoked
+    pub fn revoke_achievement(ctx: Context<RevokeAchievement>, module_id: u8, reason: u8) -> Result<()> {
+        require!((module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
+        
+        require_admin_or_moderator(
+            &ctx.accounts.config,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.role_grant.as_deref(),
+        )?;
+        
+        let user_progress = &ctx.accounts.user_progress;
+        require!(
+            (user_progress.minted_modules & (1u8 << module_id)) != 0,
+            ShadowRanchError::AchievementNotMinted
+        );
+        
+        let clock = Clock::get()?;
+        let revocation = &mut ctx.accounts.revocation;
+        revocation.learner = user_progress.authority;
+        revocation.module_id = module_id;
+        revocation.reason = reason;
+        revocation.revoked_by = ctx.accounts.authority.key();
+        revocation.revoked_at = clock.unix_timestamp;
+        revocation.bump = ctx.bumps.revocation;
+        
+        emit!(AchievementRevoked {
+            learner: user_progress.authority,
+            module_id,
+            reason,
+            revoked_by: ctx.accounts.authority.key(),
+            timestamp: clock.unix_timestamp,
+        });
+        
+        msg!("Achievement for module {} revoked for user: {}", module_id, user_progress.authority);
+        Ok(())
+    }
+
+    /// Close a learner's progress account, returning its rent to `receiver`, which must be the sponsor wallet if it paid
+    /// A tombstone keeps the minted achievements and claimed rewards for if they come back
+    pub fn close_user(ctx: Context<CloseUser>) -> Result<()> {
+        // The tombstone is only paid for the first time the learner closes their account
+        if ctx.accounts.tombstone.closed_at == 0 {
+            charge_sponsor(
+                ctx.accounts.sponsor.as_deref_mut(),
+                ctx.accounts.tombstone.to_account_info().lamports(),
+            )?;
+        }
+        
+        let user_progress = &ctx.accounts.user_progress;
+        let tombstone = &mut ctx.accounts.tombstone;
+        
+        tombstone.authority = user_progress.authority;
+        tombstone.minted_modules = user_progress.minted_modules;
+        tombstone.rewards_claimed = user_progress.rewards_claimed;
+        tombstone.streak_bonuses_claimed = user_progress.streak_bonuses_claimed;
+        tombstone.closed_at = Clock::get()?.unix_timestamp;
+        tombstone.bump = ctx.bumps.tombstone;
+        
+        msg!("User progress account closed for: {}", user_progress.authority);
+        Ok(())
+    }
+
+    /// Sponsor a learner, letting the signing wallet pay their rent and fees up to a budget
+    /// The sponsor wallet signs as `payer` in the learner's transactions and passes this account
+    pub fn create_sponsorship(
+        ctx: Context<CreateSponsorship>,
+        learner: Pubkey,
+        budget_lamports: u64,
+    ) -> Result<()> {
+        let sponsor = &mut ctx.accounts.sponsor;
+        sponsor.payer = ctx.accounts.payer.key();
+        sponsor.learner = learner;
+        sponsor.budget_lamports = budget_lamports;
+        sponsor.spent_lamports = 0;
+        sponsor.created_at = Clock::get()?.unix_timestamp;
+        sponsor.bump = ctx.bumps.sponsor;
+        
+        msg!("Sponsorship of {} lamports created for: {}", budget_lamports, learner);
+        Ok(())
+    }
+
+    /// Change the lamport budget of a sponsorship
+    /// A budget below what was already spent stops any further sponsored transactions
+    pub fn set_sponsor_budget(ctx: Context<UpdateSponsorship>, budget_lamports: u64) -> Result<()> {
+        let sponsor = &mut ctx.accounts.sponsor;
+        sponsor.budget_lamports = budget_lamports;
+        
+        msg!("Sponsorship budget set to {} lamports for: {}", budget_lamports, sponsor.learner);
+        Ok(())
+    }
+
+    /// End a sponsorship, returning its rent to the sponsor wallet
+    /// The learner pays for their own transactions from then on
+    pub fn close_sponsorship(ctx: Context<CloseSponsorship>) -> Result<()> {
+        msg!("Sponsorship closed for: {}", ctx.accounts.sponsor.learner);
+        Ok(())
+    }
+
+    /// Migrate a user progress account from an earlier layout to the current version
+    /// The account is grown in place and existing progress is carried over
+    pub fn migrate_user_progress(ctx: Context<MigrateUserProgress>) -> Result<()> {
+        let account_info = ctx.accounts.user_progress.to_account_info();
+        
+        // Only accounts still on the original layout need migrating
+        let previous = {
+            let data = account_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == UserProgress::DISCRIMINATOR,
+                ErrorCode::AccountDiscriminatorMismatch
+            );
+            require!(data.len() == UserProgressV0::LEN, ShadowRanchError::AlreadyMigrated);
+            UserProgressV0::deserialize(&mut &data[8..])?
+        };
+        
+        // Verify the signer is the authority of this progress account
+        require!(
+            ctx.accounts.authority.key() == previous.authority,
+            ShadowRanchError::Unauthorized
+        );
+        
+        // Copy the u16 bitmask into the first two bytes of the bitmap
+        let mut challenges_completed = [0u8; CHALLENGE_BITMAP_BYTES];
+        challenges_completed[..2].copy_from_slice(&previous.challenges_completed.to_le_bytes());
+        
+        // Credit the XP of the completions carried over, at the current XP curve
+        let xp = ctx.accounts.curriculum.xp_for(&challenges_completed, previous.modules_completed);
+        
+        let migrated = UserProgress {
+            version: UserProgress::CURRENT_VERSION,
+            authority: previous.authority,
+            challenges_completed,
+            modules_completed: previous.modules_completed,
+            created_at: previous.created_at,
+            updated_at: Clock::get()?.unix_timestamp,
+            minted_modules: 0,
+            rewards_claimed: [0u8; CHALLENGE_BITMAP_BYTES],
+            xp,
+            level: ctx.accounts.curriculum.level_for(xp),
+            current_streak: 0,
+            longest_streak: 0,
+            last_active_day: 0,
+            streak_bonuses_claimed: 0,
+            module_started_at: [0; MAX_MODULES],
+            sponsored_by: if ctx.accounts.sponsor.is_some() {
+                ctx.accounts.payer.key()
+            } else {
+                Pubkey::default()
+            },
+            reserved: [0u8; UserProgress::RESERVED_BYTES],
+        };
+        
+        // Grow the account and write the current layout
+        let payer_lamports = ctx.accounts.payer.lamports();
+        resize_account(
+            &account_info,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            UserProgress::LEN,
+        )?;
+        migrated.try_serialize(&mut &mut account_info.try_borrow_mut_data()?[..])?;
+        
+        // Sponsored learners have the extra rent charged against their budget
+        let spent = payer_lamports - ctx.accounts.payer.lamports();
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            spent,
+        )?;
+        
+        msg!(
+            "User progress migrated to version {} for user: {}",
+            UserProgress::CURRENT_VERSION,
+            migrated.authority
+        );
+        Ok(())
+    }
+
+    /// Initialize the global program config
+    /// The program's upgrade authority signs and becomes the program admin, the only key allowed to manage the curriculum and config
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        mint_fee_lamports: u64,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        // Only the program's upgrade authority can claim the config
+        let program_data = ProgramData::try_deserialize(&mut &ctx.accounts.program_data.try_borrow_data()?[..])?;
+        require!(
+            program_data.upgrade_authority_address == Some(ctx.accounts.admin.key()),
+            ShadowRanchError::Unauthorized
+        );
+        
+        let config = &mut ctx.accounts.config;
+        
+        config.admin = ctx.accounts.admin.key();
+        config.pending_admin = None;
+        config.paused = false;
+        config.mint_fee_lamports = mint_fee_lamports;
+        config.treasury = treasury;
+        config.leaderboards = 0;
+        config.bump = ctx.bumps.config;
+        
+        msg!("Program config initialized with admin: {}", config.admin);
+        Ok(())
+    }
+
+    /// Propose a new program admin
+    /// The transfer only takes effect once the proposed admin accepts it
+    pub fn set_admin(ctx: Context<SetAdmin>, new_admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.pending_admin = Some(new_admin);
+        
+        msg!("Admin transfer proposed to: {}", new_admin);
+        Ok(())
+    }
+
+    /// Accept a pending admin transfer
+    /// Must be signed by the proposed admin
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.pending_admin.key();
+        config.pending_admin = None;
+        
+        msg!("Admin transfer accepted by: {}", config.admin);
+        Ok(())
+    }
+
+    /// Pause the program
+    /// Every state-changing instruction fails with `ProgramPaused` until the admin unpauses
+    pub fn pause(ctx: Context<SetPaused>) -> Result<()> {
+        ctx.accounts.config.paused = true;
+        
+        msg!("Program paused");
+        Ok(())
+    }
+
+    /// Unpause the program
+    pub fn unpause(ctx: Context<SetPaused>) -> Result<()> {
+        ctx.accounts.config.paused = false;
+        
+        msg!("Program unpaused");
+        Ok(())
+    }
+
+    /// Update the achievement mint fee and the treasury it is paid to
+    /// A fee of 0 makes minting free
+    pub fn update_fees(
+        ctx: Context<UpdateFees>,
+        mint_fee_lamports: u64,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.mint_fee_lamports = mint_fee_lamports;
+        config.treasury = treasury;
+        
+        msg!("Mint fee set to {} lamports, paid to: {}", mint_fee_lamports, treasury);
+        Ok(())
+    }
+
+    /// Turn strict prerequisite mode on or off
+    /// When strict, challenges can't be completed until their module's prerequisites are
+    pub fn set_strict_prerequisites(ctx: Context<SetStrictPrerequisites>, strict: bool) -> Result<()> {
+        ctx.accounts.curriculum.strict_prerequisites = strict;
+        
+        msg!("Strict prerequisites: {}", strict);
+        Ok(())
+    }
+
+    /// Set the XP needed to reach each level, starting with level 2
+    /// Stored levels catch up with this curve the next time the learner completes a challenge or module
+    pub fn set_xp_curve(ctx: Context<SetXpCurve>, level_thresholds: Vec<u64>) -> Result<()> {
+        require!(
+            level_thresholds.len() <= Curriculum::MAX_LEVEL_THRESHOLDS
+                && level_thresholds.windows(2).all(|pair| pair[0] < pair[1]),
+            ShadowRanchError::InvalidXpCurve
+        );
+        
+        msg!("XP curve set with {} levels", level_thresholds.len() + 1);
+        ctx.accounts.curriculum.level_thresholds = level_thresholds;
+        Ok(())
+    }
+
+    /// Create a leaderboard holding the top `capacity` learners
+    /// `GLOBAL_LEADERBOARD` ranks by XP, a module id ranks by time taken to complete that module
+    pub fn create_leaderboard(ctx: Context<CreateLeaderboard>, leaderboard_id: u8, capacity: u8) -> Result<()> {
+        require!(
+            leaderboard_id == GLOBAL_LEADERBOARD || (leaderboard_id as usize) < MAX_MODULES,
+            ShadowRanchError::InvalidModuleId
+        );
+        require!(
+            capacity > 0 && capacity <= MAX_LEADERBOARD_CAPACITY,
+            ShadowRanchError::InvalidLeaderboardCapacity
+        );
+        
+        let leaderboard = &mut ctx.accounts.leaderboard;
+        leaderboard.leaderboard_id = leaderboard_id;
+        leaderboard.capacity = capacity;
+        leaderboard.entries = Vec::new();
+        leaderboard.bump = ctx.bumps.leaderboard;
+        
+        // Ranked instructions must pass the leaderboard from now on
+        ctx.accounts.config.leaderboards |= ProgramConfig::leaderboard_bit(leaderboard_id);
+        
+        msg!("Leaderboard {} created with capacity {}", leaderboard_id, capacity);
+        Ok(())
+    }
+
+    /// Initialize the curriculum registry
+    /// Modules are managed by the program admin and the instructors of each track
+    pub fn initialize_curriculum(ctx: Context<InitializeCurriculum>) -> Result<()> {
+        let curriculum = &mut ctx.accounts.curriculum;
+        
+        curriculum.modules = Vec::new();
+        curriculum.strict_prerequisites = false;
+        curriculum.level_thresholds = Vec::new();
+        curriculum.bump = ctx.bumps.curriculum;
+        
+        msg!("Curriculum initialized");
+        Ok(())
+    }
+
+    /// Grant a role to a key
+    /// Only the program admin can grant roles, instructors get one grant per track they manage
+    pub fn grant_role(
+        ctx: Context<GrantRole>,
+        role: Role,
+        grantee: Pubkey,
+        track_id: u8,
+    ) -> Result<()> {
+        // Only instructors are scoped to a track, other roles hold a single grant under track 0
+        require!(
+            (track_id as usize) < MAX_TRACKS && (role == Role::Instructor || track_id == 0),
+            ShadowRanchError::InvalidTrackId
+        );
+        
+        let role_grant = &mut ctx.accounts.role_grant;
+        role_grant.role = role;
+        role_grant.grantee = grantee;
+        role_grant.track_id = track_id;
+        role_grant.granted_at = Clock::get()?.unix_timestamp;
+        role_grant.bump = ctx.bumps.role_grant;
+        
+        msg!("Role {:?} granted to: {}", role, grantee);
+        Ok(())
+    }
+
+    /// Revoke a role, returning its rent to the admin
+    /// Attestations signed by a revoked verifier are rejected from then on
+    pub fn revoke_role(ctx: Context<RevokeRole>) -> Result<()> {
+        msg!(
+            "Role {:?} revoked from: {}",
+            ctx.accounts.role_grant.role,
+            ctx.accounts.role_grant.grantee
+        );
+        Ok(())
+    }
+
+    /// Create the collection NFT that groups all achievements of a track
+    /// Only the program admin can create collections, one per track
+    pub fn create_achievement_collection(
+        ctx: Context<CreateAchievementCollection>,
+        track_id: u8,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        require!((track_id as usize) < MAX_TRACKS, ShadowRanchError::InvalidTrackId);
+        validate_metadata_lengths(&name, &symbol, &uri)?;
+        
+        let achievement_authority_seeds: &[&[&[u8]]] = &[&[
+            b"achievement_authority",
+            &[ctx.bumps.achievement_authority],
+        ]];
+        
+        // Mint the single collection token to the achievement authority
+        let cpi_accounts = MintTo {
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            to: ctx.accounts.collection_token_account.to_account_info(),
+            authority: ctx.accounts.achievement_authority.to_account_info(),
+        };
+        
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        mint_to(cpi_ctx, 1)?;
+        
+        // Create the collection metadata as a sized collection
+        let creators = vec![
+            Creator {
+                address: ctx.accounts.achievement_authority.key(),
+                verified: true,
+                share: 100,
+            }
+        ];
+        
+        let data_v2 = DataV2 {
+            name,
+            symbol,
+            uri,
+            seller_fee_basis_points: 0,
+            creators: Some(creators),
+            collection: None,
+            uses: None,
+        };
+        
+        let cpi_accounts = CreateMetadataAccountsV3 {
+            metadata: ctx.accounts.collection_metadata.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            mint_authority: ctx.accounts.achievement_authority.to_account_info(),
+            payer: ctx.accounts.admin.to_account_info(),
+            update_authority: ctx.accounts.achievement_authority.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        create_metadata_accounts_v3(
+            cpi_ctx,
+            data_v2,
+            true, // is_mutable
+            true, // update_authority_is_signer
+            Some(CollectionDetails::V1 { size: 0 }),
+        )?;
+        
+        // Create the collection master edition
+        let cpi_accounts = CreateMasterEditionV3 {
+            edition: ctx.accounts.collection_master_edition.to_account_info(),
+            mint: ctx.accounts.collection_mint.to_account_info(),
+            update_authority: ctx.accounts.achievement_authority.to_account_info(),
+            mint_authority: ctx.accounts.achievement_authority.to_account_info(),
+            payer: ctx.accounts.admin.to_account_info(),
+            metadata: ctx.accounts.collection_metadata.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.metadata_program.to_account_info(),
+            cpi_accounts,
+            achievement_authority_seeds,
+        );
+        create_master_edition_v3(cpi_ctx, Some(0))?;
+        
+        msg!("Achievement collection created for track {}", track_id);
+        Ok(())
+    }
+
+    /// Create the achievement template for a module
+    /// Minted NFTs take their name, symbol and URI from the template, never from the learner
+    pub fn create_achievement_template(
+        ctx: Context<CreateAchievementTemplate>,
+        module_id: u8,
+        data: AchievementTemplateData,
+    ) -> Result<()> {
+        require!((module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
+        data.validate()?;
+        
+        let template = &mut ctx.accounts.template;
+        template.module_id = module_id;
+        template.bump = ctx.bumps.template;
+        template.apply(data);
+        
+        msg!("Achievement template created for module {}: {}", module_id, template.name);
+        Ok(())
+    }
+
+    /// Replace the metadata of an existing achievement template
+    /// Only affects NFTs minted afterwards
+    pub fn update_achievement_template(
+        ctx: Context<UpdateAchievementTemplate>,
+        module_id: u8,
+        data: AchievementTemplateData,
+    ) -> Result<()> {
+        data.validate()?;
+        
+        let template = &mut ctx.accounts.template;
+        template.apply(data);
+        
+        msg!("Achievement template updated for module {}: {}", module_id, template.name);
+        Ok(())
+    }
+
+    /// Create the Merkle tree that compressed achievements are minted into
+    /// Calling it again with a newly allocated tree repoints minting there, e.g. once the current tree is full
+    pub fn create_achievement_tree(
+        ctx: Context<CreateAchievementTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let achievement_tree = &mut ctx.accounts.achievement_tree;
+        achievement_tree.merkle_tree = ctx.accounts.merkle_tree.key();
+        achievement_tree.minted = 0;
+        achievement_tree.bump = ctx.bumps.achievement_tree;
+        
+        // Private tree, so only the achievement authority can mint into it
+        let mut data = bubblegum::CREATE_TREE_DISCRIMINATOR.to_vec();
+        (max_depth, max_buffer_size, Some(false)).serialize(&mut data)?;
+        
+        let accounts = vec![
+            AccountMeta::new(ctx.accounts.tree_config.key(), false),
+            AccountMeta::new(ctx.accounts.merkle_tree.key(), false),
+            AccountMeta::new(ctx.accounts.admin.key(), true),
+            AccountMeta::new_readonly(ctx.accounts.achievement_authority.key(), true), // tree creator
+            AccountMeta::new_readonly(spl_noop::ID, false),
+            AccountMeta::new_readonly(spl_account_compression::ID, false),
+            AccountMeta::new_readonly(ctx.accounts.system_program.key(), false),
+        ];
+        let account_infos = [
+            ctx.accounts.tree_config.to_account_info(),
+            ctx.accounts.merkle_tree.to_account_info(),
+            ctx.accounts.admin.to_account_info(),
+            ctx.accounts.achievement_authority.to_account_info(),
+            ctx.accounts.log_wrapper.to_account_info(),
+            ctx.accounts.compression_program.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+        ];
+        invoke_signed(
+            &Instruction {
+                program_id: bubblegum::ID,
+                accounts,
+                data,
+            },
+            &account_infos,
+            &[&[b"achievement_authority", &[ctx.bumps.achievement_authority]]],
+        )?;
+        
+        msg!(
+            "Achievement tree created: {} (depth {}, buffer {})",
+            achievement_tree.merkle_tree,
+            max_depth,
+            max_buffer_size
+        );
+        Ok(())
+    }
+
+    /// Register a new module in the curriculum
+    /// The curriculum account is grown to make room for the new entry, paid by the signer
+    pub fn create_module(ctx: Context<CreateModule>, module: ModuleConfig) -> Result<()> {
+        module.validate()?;
+        require_admin_or_instructor(
+            &ctx.accounts.config,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.role_grant.as_deref(),
+            module.track_id,
+        )?;
+        
+        let curriculum = &mut ctx.accounts.curriculum;
+        require!(
+            curriculum.find_module(module.module_id).is_none(),
+            ShadowRanchError::ModuleAlreadyExists
+        );
+        curriculum.validate_prerequisites(&module)?;
+        
+        msg!("Module {} created: {}", module.module_id, module.title);
+        curriculum.modules.push(module);
+        Ok(())
+    }
+
+    /// Replace the configuration of an existing module
+    /// Challenge ids, prerequisites and display metadata can all be changed
+    pub fn update_module(ctx: Context<UpdateModule>, module: ModuleConfig) -> Result<()> {
+        module.validate()?;
+        
+        let curriculum = &mut ctx.accounts.curriculum;
+        curriculum.validate_prerequisites(&module)?;
+        let existing = curriculum.modules
+            .iter_mut()
+            .find(|existing| existing.module_id == module.module_id)
+            .ok_or(ShadowRanchError::InvalidModuleId)?;
+        
+        // Instructors can only edit modules of their track, and can't move them to another one
+        for track_id in [existing.track_id, module.track_id] {
+            require_admin_or_instructor(
+                &ctx.accounts.config,
+                &ctx.accounts.authority.key(),
+                ctx.accounts.role_grant.as_deref(),
+                track_id,
+            )?;
+        }
+        
+        msg!("Module {} updated: {}", module.module_id, module.title);
+        *existing = module;
+        Ok(())
+    }
+
+    /// Create the RanchCoin mint and the reward table for challenges
+    /// Every challenge starts out paying `default_reward`, only the program can mint RanchCoin
+    pub fn initialize_ranch_coin(ctx: Context<InitializeRanchCoin>, default_reward: u64) -> Result<()> {
+        let reward_config = &mut ctx.accounts.reward_config;
+        reward_config.challenge_rewards = vec![default_reward; MAX_CHALLENGES];
+        reward_config.streak_bonuses = [0; STREAK_MILESTONES.len()];
+        reward_config.mint_bump = ctx.bumps.ranch_coin_mint;
+        reward_config.bump = ctx.bumps.reward_config;
+        
+        msg!("RanchCoin mint created: {}", ctx.accounts.ranch_coin_mint.key());
+        Ok(())
+    }
+
+    /// Set the RanchCoin reward paid out for a single challenge
+    /// Rewards already claimed are not affected
+    pub fn set_challenge_reward(
+        ctx: Context<UpdateRewardConfig>,
+        challenge_id: u8,
+        amount: u64,
+    ) -> Result<()> {
+        require!((challenge_id as usize) < MAX_CHALLENGES, ShadowRanchError::InvalidChallengeId);
+        
+        ctx.accounts.reward_config.challenge_rewards[challenge_id as usize] = amount;
+        
+        msg!("Challenge {} reward set to {} RanchCoin", challenge_id, amount);
+        Ok(())
+    }
+
+    /// Set the RanchCoin bonuses paid for reaching each streak milestone
+    /// Bonuses are off until set, a milestone with a bonus of 0 stays claimable until its bonus is set
+    pub fn set_streak_bonuses(
+        ctx: Context<UpdateRewardConfig>,
+        bonuses: [u64; STREAK_MILESTONES.len()],
+    ) -> Result<()> {
+        ctx.accounts.reward_config.streak_bonuses = bonuses;
+        
+        msg!("Streak bonuses set to {:?} RanchCoin", bonuses);
+        Ok(())
+    }
+
+    /// Claim the RanchCoin reward for a completed challenge into the learner's associated token account
+    /// Each challenge can only be claimed once, even if it is revoked and completed again
+    pub fn claim_challenge_reward(ctx: Context<ClaimReward>, challenge_id: u8) -> Result<()> {
+        require!((challenge_id as usize) < MAX_CHALLENGES, ShadowRanchError::InvalidChallengeId);
+        
+        let user_progress = &mut ctx.accounts.user_progress;
+        require!(
+            user_progress.is_challenge_completed(challenge_id),
+            ShadowRanchError::ChallengeNotCompleted
+        );
+        require!(
+            !user_progress.is_reward_claimed(challenge_id),
+            ShadowRanchError::RewardAlreadyClaimed
+        );
+        user_progress.set_reward_claimed(challenge_id);
+        
+        let amount = ctx.accounts.reward_config.challenge_rewards[challenge_id as usize];
+        mint_ranch_coin_reward(ctx.accounts, amount)?;
+        
+        msg!(
+            "Claimed {} RanchCoin for challenge {} by user: {}",
+            amount,
+            challenge_id,
+            ctx.accounts.authority.key()
+        );
+        Ok(())
+    }
+
+    /// Claim the RanchCoin bonus for every streak milestone reached but not yet claimed
+    /// Milestones count as reached once the learner's longest streak gets there, and stay unclaimed while their bonus is 0
+    pub fn claim_streak_bonus(ctx: Context<ClaimReward>) -> Result<()> {
+        let user_progress = &mut ctx.accounts.user_progress;
+        let mut amount = 0u64;
+        
+        for (index, &milestone) in STREAK_MILESTONES.iter().enumerate() {
+            let mask = 1u8 << index;
+            let bonus = ctx.accounts.reward_config.streak_bonuses[index];
+            if bonus > 0 && user_progress.longest_streak >= milestone && (user_progress.streak_bonuses_claimed & mask) == 0 {
+                user_progress.streak_bonuses_claimed |= mask;
+                amount = amount.saturating_add(bonus);
+            }
+        }
+        require!(amount > 0, ShadowRanchError::NoStreakBonus);
+        
+        mint_ranch_coin_reward(ctx.accounts, amount)?;
+        
+        msg!("Claimed {} RanchCoin in streak bonuses by user: {}", amount, ctx.accounts.authority.key());
+        Ok(())
+    }
+
+    /// Create the ranch shop's price list
+    /// Buildings, upgrades and characters are paid for by burning RanchCoin
+    pub fn initialize_ranch_config(ctx: Context<InitializeRanchConfig>, prices: RanchPrices) -> Result<()> {
+        let ranch_config = &mut ctx.accounts.ranch_config;
+        ranch_config.prices = prices;
+        ranch_config.bump = ctx.bumps.ranch_config;
+        
+        msg!("Ranch config initialized");
+        Ok(())
+    }
+
+    /// Update the ranch shop's price list
+    /// Only the program admin can change prices
+    pub fn update_ranch_prices(ctx: Context<UpdateRanchPrices>, prices: RanchPrices) -> Result<()> {
+        ctx.accounts.ranch_config.prices = prices;
+        
+        msg!("Ranch prices updated");
+        Ok(())
+    }
+
+    /// Initialize a learner's ranch
+    /// Buildings and characters are separate PDAs numbered by the ranch
+    pub fn initialize_ranch(ctx: Context<InitializeRanch>) -> Result<()> {
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            ctx.accounts.ranch.to_account_info().lamports(),
+        )?;
+        
+        let ranch = &mut ctx.accounts.ranch;
+        ranch.authority = ctx.accounts.authority.key();
+        ranch.building_count = 0;
+        ranch.character_count = 0;
+        ranch.created_at = Clock::get()?.unix_timestamp;
+        ranch.bump = ctx.bumps.ranch;
+        
+        msg!("Ranch initialized for: {}", ranch.authority);
+        Ok(())
+    }
+
+    /// Buy a building and place it on the ranch
+    /// The building price is burned from the learner's RanchCoin
+    pub fn buy_building(ctx: Context<BuyBuilding>, kind: u8, x: u16, y: u16) -> Result<()> {
+        validate_position(x, y)?;
+        
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            ctx.accounts.building.to_account_info().lamports(),
+        )?;
+        
+        burn_ranch_coin(
+            &ctx.accounts.token_program,
+            &ctx.accounts.ranch_coin_mint,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.authority,
+            ctx.accounts.ranch_config.prices.building_price,
+        )?;
+        
+        let ranch = &mut ctx.accounts.ranch;
+        let building = &mut ctx.accounts.building;
+        building.ranch = ranch.key();
+        building.id = ranch.building_count;
+        building.kind = kind;
+        building.level = 1;
+        building.x = x;
+        building.y = y;
+        building.is_active = true;
+        building.bump = ctx.bumps.building;
+        
+        ranch.building_count = ranch.building_count
+            .checked_add(1)
+            .ok_or(ShadowRanchError::RanchFull)?;
+        
+        msg!("Building {} bought for ranch: {}", building.id, building.ranch);
+        Ok(())
+    }
+
+    /// Upgrade a building by one level
+    /// Each upgrade costs the upgrade price times the building's current level
+    pub fn upgrade_building(ctx: Context<UpgradeBuilding>) -> Result<()> {
+        let level = ctx.accounts.building.level;
+        require!(level < MAX_BUILDING_LEVEL, ShadowRanchError::MaxLevelReached);
+        
+        burn_ranch_coin(
+            &ctx.accounts.token_program,
+            &ctx.accounts.ranch_coin_mint,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.authority,
+            ctx.accounts.ranch_config.prices.upgrade_price.saturating_mul(level as u64),
+        )?;
+        
+        let building = &mut ctx.accounts.building;
+        building.level = level + 1;
+        
+        msg!("Building {} upgraded to level {}", building.id, building.level);
+        Ok(())
+    }
+
+    /// Recruit a character onto the ranch
+    /// The price depends on the character's rarity and is burned from the learner's RanchCoin
+    pub fn recruit_character(
+        ctx: Context<RecruitCharacter>,
+        kind: u8,
+        rarity: Rarity,
+        x: u16,
+        y: u16,
+    ) -> Result<()> {
+        validate_position(x, y)?;
+        
+        charge_sponsor(
+            ctx.accounts.sponsor.as_deref_mut(),
+            ctx.accounts.character.to_account_info().lamports(),
+        )?;
+        
+        burn_ranch_coin(
+            &ctx.accounts.token_program,
+            &ctx.accounts.ranch_coin_mint,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.authority,
+            ctx.accounts.ranch_config.prices.character_prices[rarity as usize],
+        )?;
+        
+        let ranch = &mut ctx.accounts.ranch;
+        let character = &mut ctx.accounts.character;
+        character.ranch = ranch.key();
+        character.id = ranch.character_count;
+        character.kind = kind;
+        character.rarity = rarity;
+        character.level = 1;
+        character.x = x;
+        character.y = y;
+        character.is_active = true;
+        character.bump = ctx.bumps.character;
+        
+        ranch.character_count = ranch.character_count
+            .checked_add(1)
+            .ok_or(ShadowRanchError::RanchFull)?;
+        
+        msg!("Character {} recruited for ranch: {}", character.id, character.ranch);
+        Ok(())
+    }
+
+    /// Level up a character by one level
+    /// Each level costs the upgrade price times the character's current level, like building upgrades
+    pub fn level_up_character(ctx: Context<LevelUpCharacter>) -> Result<()> {
+        let level = ctx.accounts.character.level;
+        require!(level < MAX_CHARACTER_LEVEL, ShadowRanchError::MaxLevelReached);
+        
+        burn_ranch_coin(
+            &ctx.accounts.token_program,
+            &ctx.accounts.ranch_coin_mint,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.authority,
+            ctx.accounts.ranch_config.prices.upgrade_price.saturating_mul(level as u64),
+        )?;
+        
+        let character = &mut ctx.accounts.character;
+        character.level = level + 1;
+        
+        msg!("Character {} leveled up to {}", character.id, character.level);
+        Ok(())
+    }
+
+    /// Move a building or a character to a new position on the ranch
+    /// Exactly one of `building` and `character` must be passed
+    pub fn move_entity(ctx: Context<UpdateEntity>, x: u16, y: u16) -> Result<()> {
+        validate_position(x, y)?;
+        
+        match (ctx.accounts.building.as_mut(), ctx.accounts.character.as_mut()) {
+            (Some(building), None) => {
+                building.x = x;
+                building.y = y;
+                msg!("Building {} moved to ({}, {})", building.id, x, y);
+            }
+            (None, Some(character)) => {
+                character.x = x;
+                character.y = y;
+                msg!("Character {} moved to ({}, {})", character.id, x, y);
+            }
+            _ => return err!(ShadowRanchError::InvalidEntity),
+        }
+        
+        Ok(())
+    }
+
+    /// Show or hide a building or a character on the ranch
+    /// Exactly one of `building` and `character` must be passed
+    pub fn set_entity_active(ctx: Context<UpdateEntity>, is_active: bool) -> Result<()> {
+        match (ctx.accounts.building.as_mut(), ctx.accounts.character.as_mut()) {
+            (Some(building), None) => {
+                building.is_active = is_active;
+                msg!("Building {} active: {}", building.id, is_active);
+            }
+            (None, Some(character)) => {
+                character.is_active = is_active;
+                msg!("Character {} active: {}", character.id, is_active);
+            }
+            _ => return err!(ShadowRanchError::InvalidEntity),
+        }
+        
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeUser<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = UserProgress::LEN,
+        seeds = [b"user_progress", authority.key().as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    /// CHECK: Tombstone left by `close_user`, only read when the learner closed a previous account
+    #[account(
+        seeds = [b"user_tombstone", authority.key().as_ref()],
+        bump
+    )]
+    pub tombstone: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    /// CHECK: This is the user's authority key
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(track_id: u8)]
+pub struct InitializeTrack<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = TrackProgress::LEN,
+        seeds = [b"track_progress", authority.key().as_ref(), &[track_id]],
+        bump
+    )]
+    pub track_progress: Account<'info, TrackProgress>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CompleteChallenge<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[Role::Verifier as u8], &[verifier.track_id], verifier.grantee.as_ref()],
+        bump = verifier.bump
+    )]
+    pub verifier: Account<'info, RoleGrant>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    /// The learner's progress in the track of the completed item, updated alongside `user_progress`
+    #[account(
+        mut,
+        seeds = [b"track_progress", user_progress.authority.as_ref(), &[track_progress.track_id]],
+        bump = track_progress.bump
+    )]
+    pub track_progress: Option<Account<'info, TrackProgress>>,
+    
+    /// The learner, or the verifier signing the completion directly
+    pub authority: Signer<'info>,
+    
+    /// CHECK: The instructions sysvar, used to inspect the Ed25519 attestation
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct CompleteModule<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    /// The learner's progress in the track of the completed item, updated alongside `user_progress`
+    #[account(
+        mut,
+        seeds = [b"track_progress", user_progress.authority.as_ref(), &[track_progress.track_id]],
+        bump = track_progress.bump
+    )]
+    pub track_progress: Option<Account<'info, TrackProgress>>,
+    
+    /// Top learners by XP, required once it has been created
+    #[account(
+        mut,
+        seeds = [b"leaderboard".as_ref(), &[GLOBAL_LEADERBOARD]],
+        bump = global_leaderboard.bump
+    )]
+    pub global_leaderboard: Option<Account<'info, Leaderboard>>,
+    
+    /// Fastest learners through this module, required once it has been created
+    #[account(
+        mut,
+        seeds = [b"leaderboard".as_ref(), &[module_id]],
+        bump = module_leaderboard.bump
+    )]
+    pub module_leaderboard: Option<Account<'info, Leaderboard>>,
+    
+    /// Role held by the signer when acting for the learner
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[role_grant.role as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The learner, or a verifier or instructor acting for them
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct MintAchievementNft<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    /// CHECK: PDA that holds mint, freeze and update authority over achievements, never read or written
+    #[account(
+        seeds = [b"achievement_authority"],
+        bump
+    )]
+    pub achievement_authority: UncheckedAccount<'info>,
+    
+    /// One achievement mint per (user, module)
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"achievement_mint", user_progress.authority.as_ref(), &[module_id]],
+        bump,
+        mint::decimals = 0,
+        mint::authority = achievement_authority,
+        mint::freeze_authority = achievement_authority
+    )]
+    pub mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = learner
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Created by the Token Metadata program, address checked via seeds
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+    
+    /// CHECK: Created by the Token Metadata program, address checked via seeds
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub master_edition: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    #[account(
+        seeds = [b"achievement_template".as_ref(), &[module_id]],
+        bump = template.bump
+    )]
+    pub template: Account<'info, AchievementTemplate>,
+    
+    /// The collection of the track the module belongs to
+    #[account(
+        seeds = [b"collection_mint".as_ref(), &[curriculum.track_of(module_id)]],
+        bump
+    )]
+    pub collection_mint: Account<'info, Mint>,
+    
+    /// CHECK: Collection metadata, address checked via seeds and updated by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    
+    /// CHECK: Collection master edition, address checked via seeds
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the mint when `payer` isn't the one minting
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), user_progress.authority.as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    /// Role held by the signer when acting for the learner
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[role_grant.role as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The learner, or an instructor minting for them
+    pub authority: Signer<'info>,
+    
+    /// CHECK: The learner receiving the achievement, checked against the progress account
+    #[account(address = user_progress.authority)]
+    pub learner: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives the mint fee, checked against the config
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct MintAchievementNft2022<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    /// CHECK: PDA that holds mint, freeze and update authority over achievements, never read or written
+    #[account(
+        seeds = [b"achievement_authority"],
+        bump
+    )]
+    pub achievement_authority: UncheckedAccount<'info>,
+    
+    /// CHECK: One Token-2022 achievement mint per (user, module), created with its extensions in the instruction
+    #[account(
+        mut,
+        seeds = [b"achievement_mint_2022", user_progress.authority.as_ref(), &[module_id]],
+        bump
+    )]
+    pub mint: UncheckedAccount<'info>,
+    
+    /// CHECK: Created by the Associated Token program, which checks the address
+    #[account(mut)]
+    pub user_token_account: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    #[account(
+        seeds = [b"achievement_template".as_ref(), &[module_id]],
+        bump = template.bump
+    )]
+    pub template: Account<'info, AchievementTemplate>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the mint when `payer` isn't the one minting
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), user_progress.authority.as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    /// Role held by the signer when acting for the learner
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[role_grant.role as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The learner, or an instructor minting for them
+    pub authority: Signer<'info>,
+    
+    /// CHECK: The learner receiving the achievement, checked against the progress account
+    #[account(address = user_progress.authority)]
+    pub learner: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives the mint fee, checked against the config
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token2022>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct MintCompressedAchievement<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    /// CHECK: PDA that is tree creator and collection authority for compressed achievements, never read or written
+    #[account(
+        seeds = [b"achievement_authority"],
+        bump
+    )]
+    pub achievement_authority: UncheckedAccount<'info>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement_tree"],
+        bump = achievement_tree.bump,
+        has_one = merkle_tree
+    )]
+    pub achievement_tree: Account<'info, AchievementTree>,
+    
+    /// CHECK: Bubblegum's tree config, address checked via seeds
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum_program.key()
+    )]
+    pub tree_config: UncheckedAccount<'info>,
+    
+    /// CHECK: The Merkle tree, checked against `achievement_tree` and written by Account Compression
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    #[account(
+        seeds = [b"achievement_template".as_ref(), &[module_id]],
+        bump = template.bump
+    )]
+    pub template: Account<'info, AchievementTemplate>,
+    
+    /// The collection of the track the module belongs to
+    #[account(
+        seeds = [b"collection_mint".as_ref(), &[curriculum.track_of(module_id)]],
+        bump
+    )]
+    pub collection_mint: Account<'info, Mint>,
+    
+    /// CHECK: Collection metadata, address checked via seeds and updated by the Token Metadata program
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    
+    /// CHECK: Collection master edition, address checked via seeds
+    #[account(
+        seeds = [b"metadata", metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    
+    /// CHECK: Bubblegum's signer for collection CPIs, address checked via seeds
+    #[account(
+        seeds = [b"collection_cpi"],
+        bump,
+        seeds::program = bubblegum_program.key()
+    )]
+    pub bubblegum_signer: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the mint when `payer` isn't the one minting
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), user_progress.authority.as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    /// Role held by the signer when acting for the learner
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[role_grant.role as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The learner, or an instructor minting for them
+    pub authority: Signer<'info>,
+    
+    /// CHECK: The learner receiving the achievement, checked against the progress account
+    #[account(address = user_progress.authority)]
+    pub learner: UncheckedAccount<'info>,
+    
+    /// CHECK: Receives the mint fee, checked against the config
+    #[account(mut, address = config.treasury)]
+    pub treasury: UncheckedAccount<'info>,
+    
+    /// CHECK: The Bubblegum program, checked by address
+    #[account(address = bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    
+    /// CHECK: The SPL Noop program, checked by address
+    #[account(address = spl_noop::ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+    
+    /// CHECK: The SPL Account Compression program, checked by address
+    #[account(address = spl_account_compression::ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    
+    pub metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(track_id: u8)]
+pub struct CreateAchievementCollection<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// CHECK: PDA that holds mint, freeze and update authority over achievements, never read or written
+    #[account(
+        seeds = [b"achievement_authority"],
+        bump
+    )]
+    pub achievement_authority: UncheckedAccount<'info>,
+    
+    /// One collection mint per track
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"collection_mint".as_ref(), &[track_id]],
+        bump,
+        mint::decimals = 0,
+        mint::authority = achievement_authority,
+        mint::freeze_authority = achievement_authority
+    )]
+    pub collection_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = collection_mint,
+        associated_token::authority = achievement_authority
+    )]
+    pub collection_token_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Created by the Token Metadata program, address checked via seeds
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub collection_metadata: UncheckedAccount<'info>,
+    
+    /// CHECK: Created by the Token Metadata program, address checked via seeds
+    #[account(
+        mut,
+        seeds = [b"metadata", metadata_program.key().as_ref(), collection_mint.key().as_ref(), b"edition"],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct CreateAchievementTemplate<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = AchievementTemplate::LEN,
+        seeds = [b"achievement_template".as_ref(), &[module_id]],
+        bump
+    )]
+    pub template: Account<'info, AchievementTemplate>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct UpdateAchievementTemplate<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"achievement_template".as_ref(), &[module_id]],
+        bump = template.bump
+    )]
+    pub template: Account<'info, AchievementTemplate>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAchievementTree<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// CHECK: PDA that becomes the tree creator, never read or written
+    #[account(
+        seeds = [b"achievement_authority"],
+        bump
+    )]
+    pub achievement_authority: UncheckedAccount<'info>,
+    
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = AchievementTree::LEN,
+        seeds = [b"achievement_tree"],
+        bump
+    )]
+    pub achievement_tree: Account<'info, AchievementTree>,
+    
+    /// CHECK: Bubblegum's tree config, address checked via seeds and created by Bubblegum
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum_program.key()
+    )]
+    pub tree_config: UncheckedAccount<'info>,
+    
+    /// CHECK: Allocated by the admin for Account Compression, which initializes it
+    #[account(mut, owner = spl_account_compression::ID)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    /// CHECK: The Bubblegum program, checked by address
+    #[account(address = bubblegum::ID)]
+    pub bubblegum_program: UncheckedAccount<'info>,
+    
+    /// CHECK: The SPL Noop program, checked by address
+    #[account(address = spl_noop::ID)]
+    pub log_wrapper: UncheckedAccount<'info>,
+    
+    /// CHECK: The SPL Account Compression program, checked by address
+    #[account(address = spl_account_compression::ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeProgress<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    /// The learner's progress in the revoked item's track, cleared alongside `user_progress`
+    #[account(
+        mut,
+        seeds = [b"track_progress", user_progress.authority.as_ref(), &[track_progress.track_id]],
+        bump = track_progress.bump
+    )]
+    pub track_progress: Option<Account<'info, TrackProgress>>,
+    
+    /// Top learners by XP, updated with the learner's remaining XP, required once it has been created
+    #[account(
+        mut,
+        seeds = [b"leaderboard".as_ref(), &[GLOBAL_LEADERBOARD]],
+        bump = global_leaderboard.bump
+    )]
+    pub global_leaderboard: Option<Account<'info, Leaderboard>>,
+    
+    /// Fastest learners through the revoked module, only used and then required by `revoke_module` once created
+    #[account(
+        mut,
+        seeds = [b"leaderboard".as_ref(), &[module_leaderboard.leaderboard_id]],
+        bump = module_leaderboard.bump
+    )]
+    pub module_leaderboard: Option<Account<'info, Leaderboard>>,
+    
+    /// Moderator grant, required unless the signer is the program admin
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[Role::Moderator as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The program admin, or a moderator
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(module_id: u8)]
+pub struct RevokeAchievement<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        seeds = [b"user_progress", user_progress.authority.as_ref()],
+        bump
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    /// One receipt per revoked achievement
+    #[account(
+        init,
+        payer = authority,
+        space = AchievementRevocation::LEN,
+        seeds = [b"achievement_revocation", user_progress.authority.as_ref(), &[module_id]],
+        bump
+    )]
+    pub revocation: Account<'info, AchievementRevocation>,
+    
+    /// Moderator grant, required unless the signer is the program admin
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[Role::Moderator as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The program admin, or a moderator
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUser<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        close = receiver,
+        seeds = [b"user_progress", authority.key().as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    /// Outlives the progress account, so a reopened account starts from the same mint receipts
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = UserTombstone::LEN,
+        seeds = [b"user_tombstone", authority.key().as_ref()],
+        bump
+    )]
+    pub tombstone: Account<'info, UserTombstone>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the tombstone's rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    /// CHECK: Any account the learner wants the rent sent to, or the sponsor wallet if a sponsor paid it
+    #[account(
+        mut,
+        constraint = user_progress.sponsored_by == Pubkey::default()
+            || receiver.key() == user_progress.sponsored_by @ ShadowRanchError::SponsorRefundRequired
+    )]
+    pub receiver: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(learner: Pubkey)]
+pub struct CreateSponsorship<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// One sponsorship per (sponsor wallet, learner)
+    #[account(
+        init,
+        payer = payer,
+        space = Sponsor::LEN,
+        seeds = [b"sponsor", payer.key().as_ref(), learner.as_ref()],
+        bump
+    )]
+    pub sponsor: Account<'info, Sponsor>,
+    
+    /// The sponsor wallet, which pays for the learner from then on
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateSponsorship<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), sponsor.learner.as_ref()],
+        bump = sponsor.bump,
+        has_one = payer
+    )]
+    pub sponsor: Account<'info, Sponsor>,
+    
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSponsorship<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"sponsor", payer.key().as_ref(), sponsor.learner.as_ref()],
+        bump = sponsor.bump,
+        has_one = payer
+    )]
+    pub sponsor: Account<'info, Sponsor>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserProgress<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// CHECK: Deserialized manually since older layouts don't match `UserProgress`
+    #[account(
+        mut,
+        seeds = [b"user_progress", authority.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub user_progress: UncheckedAccount<'info>,
+    
+    #[account(
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = ProgramConfig::LEN,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// Must be the program's upgrade authority, so the config can't be claimed by whoever calls first
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::ShadowRanchProgram>,
+    
+    /// CHECK: The program's ProgramData account, checked against `program` and read in the instruction
+    pub program_data: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.pending_admin == Some(pending_admin.key()) @ ShadowRanchError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    pub pending_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCurriculum<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = Curriculum::space(0),
+        seeds = [b"curriculum"],
+        bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetStrictPrerequisites<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetXpCurve<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaderboard_id: u8, capacity: u8)]
+pub struct CreateLeaderboard<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = Leaderboard::space(capacity as usize),
+        seeds = [b"leaderboard".as_ref(), &[leaderboard_id]],
+        bump
+    )]
+    pub leaderboard: Account<'info, Leaderboard>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(role: Role, grantee: Pubkey, track_id: u8)]
+pub struct GrantRole<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// One grant per (role, track, key), so instructors can manage several tracks
+    #[account(
+        init,
+        payer = admin,
+        space = RoleGrant::LEN,
+        seeds = [b"role_grant".as_ref(), &[role as u8], &[track_id], grantee.as_ref()],
+        bump
+    )]
+    pub role_grant: Account<'info, RoleGrant>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeRole<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"role_grant".as_ref(), &[role_grant.role as u8], &[role_grant.track_id], role_grant.grantee.as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Account<'info, RoleGrant>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateModule<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"curriculum"],
+        bump = curriculum.bump,
+        realloc = Curriculum::space(curriculum.modules.len() + 1),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    /// Instructor grant, required unless the signer is the program admin
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[Role::Instructor as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The program admin, or an instructor of the module's track
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateModule<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"curriculum"],
+        bump = curriculum.bump
+    )]
+    pub curriculum: Account<'info, Curriculum>,
+    
+    /// Instructor grant, required unless the signer is the program admin
+    #[account(
+        seeds = [b"role_grant".as_ref(), &[Role::Instructor as u8], &[role_grant.track_id], authority.key().as_ref()],
+        bump = role_grant.bump
+    )]
+    pub role_grant: Option<Account<'info, RoleGrant>>,
+    
+    /// The program admin, or an instructor of the module's track
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRanchCoin<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    /// The RanchCoin mint, which is its own mint authority
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"ranch_coin_mint"],
+        bump,
+        mint::decimals = RANCH_COIN_DECIMALS,
+        mint::authority = ranch_coin_mint
+    )]
+    pub ranch_coin_mint: Account<'info, Mint>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = RewardConfig::LEN,
+        seeds = [b"reward_config"],
+        bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRewardConfig<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"reward_config"],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReward<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"user_progress", authority.key().as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub user_progress: Account<'info, UserProgress>,
+    
+    #[account(
+        seeds = [b"reward_config"],
+        bump = reward_config.bump
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch_coin_mint"],
+        bump = reward_config.mint_bump
+    )]
+    pub ranch_coin_mint: Account<'info, Mint>,
+    
+    /// CHECK: Created by the Associated Token program if needed, which checks the address
+    #[account(mut)]
+    pub learner_token_account: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the token account's rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRanchConfig<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = admin,
+        space = RanchConfig::LEN,
+        seeds = [b"ranch_config"],
+        bump
+    )]
+    pub ranch_config: Account<'info, RanchConfig>,
+    
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRanchPrices<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch_config"],
+        bump = ranch_config.bump
+    )]
+    pub ranch_config: Account<'info, RanchConfig>,
+    
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRanch<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        init,
+        payer = payer,
+        space = Ranch::LEN,
+        seeds = [b"ranch", authority.key().as_ref()],
+        bump
+    )]
+    pub ranch: Account<'info, Ranch>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the ranch's rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyBuilding<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch", authority.key().as_ref()],
+        bump = ranch.bump,
+        has_one = authority
+    )]
+    pub ranch: Account<'info, Ranch>,
+    
+    /// Numbered by the ranch's building count
+    #[account(
+        init,
+        payer = payer,
+        space = Building::LEN,
+        seeds = [b"building", ranch.key().as_ref(), &ranch.building_count.to_le_bytes()],
+        bump
+    )]
+    pub building: Account<'info, Building>,
+    
+    #[account(
+        seeds = [b"ranch_config"],
+        bump = ranch_config.bump
+    )]
+    pub ranch_config: Account<'info, RanchConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch_coin_mint"],
+        bump
+    )]
+    pub ranch_coin_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = ranch_coin_mint,
+        associated_token::authority = authority
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the building's rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeBuilding<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        seeds = [b"ranch", authority.key().as_ref()],
+        bump = ranch.bump,
+        has_one = authority
+    )]
+    pub ranch: Account<'info, Ranch>,
+    
+    #[account(
+        mut,
+        seeds = [b"building", ranch.key().as_ref(), &building.id.to_le_bytes()],
+        bump = building.bump,
+        has_one = ranch
+    )]
+    pub building: Account<'info, Building>,
+    
+    #[account(
+        seeds = [b"ranch_config"],
+        bump = ranch_config.bump
+    )]
+    pub ranch_config: Account<'info, RanchConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch_coin_mint"],
+        bump
+    )]
+    pub ranch_coin_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = ranch_coin_mint,
+        associated_token::authority = authority
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RecruitCharacter<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch", authority.key().as_ref()],
+        bump = ranch.bump,
+        has_one = authority
+    )]
+    pub ranch: Account<'info, Ranch>,
+    
+    /// Numbered by the ranch's character count
+    #[account(
+        init,
+        payer = payer,
+        space = Character::LEN,
+        seeds = [b"character", ranch.key().as_ref(), &ranch.character_count.to_le_bytes()],
+        bump
+    )]
+    pub character: Account<'info, Character>,
+    
+    #[account(
+        seeds = [b"ranch_config"],
+        bump = ranch_config.bump
+    )]
+    pub ranch_config: Account<'info, RanchConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch_coin_mint"],
+        bump
+    )]
+    pub ranch_coin_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = ranch_coin_mint,
+        associated_token::authority = authority
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    /// Sponsorship charged for the character's rent when `payer` isn't the learner
+    #[account(
+        mut,
+        seeds = [b"sponsor", payer.key().as_ref(), authority.key().as_ref()],
+        bump = sponsor.bump
+    )]
+    pub sponsor: Option<Account<'info, Sponsor>>,
+    
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct LevelUpCharacter<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        seeds = [b"ranch", authority.key().as_ref()],
+        bump = ranch.bump,
+        has_one = authority
+    )]
+    pub ranch: Account<'info, Ranch>,
+    
+    #[account(
+        mut,
+        seeds = [b"character", ranch.key().as_ref(), &character.id.to_le_bytes()],
+        bump = character.bump,
+        has_one = ranch
+    )]
+    pub character: Account<'info, Character>,
+    
+    #[account(
+        seeds = [b"ranch_config"],
+        bump = ranch_config.bump
+    )]
+    pub ranch_config: Account<'info, RanchConfig>,
+    
+    #[account(
+        mut,
+        seeds = [b"ranch_coin_mint"],
+        bump
+    )]
+    pub ranch_coin_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        associated_token::mint = ranch_coin_mint,
+        associated_token::authority = authority
+    )]
+    pub authority_token_account: Account<'info, TokenAccount>,
+    
+    pub authority: Signer<'info>,
+    
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEntity<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = !config.paused @ ShadowRanchError::ProgramPaused
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    
+    #[account(
+        seeds = [b"ranch", authority.key().as_ref()],
+        bump = ranch.bump,
+        has_one = authority
     )]
-    pub user_progress: Account<'info, UserProgress>,
+    pub ranch: Account<'info, Ranch>,
+    
+    #[account(
+        mut,
+        seeds = [b"building", ranch.key().as_ref(), &building.id.to_le_bytes()],
+        bump = building.bump,
+        has_one = ranch
+    )]
+    pub building: Option<Account<'info, Building>>,
+    
+    #[account(
+        mut,
+        seeds = [b"character", ranch.key().as_ref(), &character.id.to_le_bytes()],
+        bump = character.bump,
+        has_one = ranch
+    )]
+    pub character: Option<Account<'info, Character>>,
+    
+    pub authority: Signer<'info>,
+}
+
+#[account]
+pub struct UserProgress {
+    /// Layout version of this account, bumped whenever `migrate_user_progress` has work to do
+    pub version: u8,
+    
+    /// The public key of the user who owns this progress account
+    pub authority: Pubkey,
+    
+    /// Bitmap tracking completion of up to 256 challenges
+    /// Each bit represents a challenge: bit 0 of byte 0 = challenge 0, bit 1 of byte 0 = challenge 1, etc.
+    pub challenges_completed: [u8; CHALLENGE_BITMAP_BYTES],
+    
+    /// Bitmask tracking completion of up to 8 modules
+    /// Each bit represents a module: bit 0 = module 0, bit 1 = module 1, etc.
+    pub modules_completed: u8,
+    
+    /// Timestamp when the account was created
+    pub created_at: i64,
+    
+    /// Timestamp when the account was last updated
+    pub updated_at: i64,
+    
+    /// Bitmask tracking which modules have had their achievement NFT minted
+    /// Each bit represents a module, matching `modules_completed`
+    pub minted_modules: u8,
+    
+    /// Bitmap tracking which challenges have had their RanchCoin reward claimed
+    /// Each bit represents a challenge, matching `challenges_completed`
+    pub rewards_claimed: [u8; CHALLENGE_BITMAP_BYTES],
+    
+    /// Experience earned from challenges and modules
+    pub xp: u64,
+    
+    /// Level reached with `xp`, following the curriculum's XP curve
+    pub level: u16,
+    
+    /// Consecutive days with at least one completion, up to `last_active_day`
+    pub current_streak: u16,
+    
+    /// Longest daily streak ever reached
+    pub longest_streak: u16,
+    
+    /// Day of the last completion, in days since the Unix epoch
+    pub last_active_day: i64,
+    
+    /// Bitmask tracking which streak milestone bonuses have been claimed
+    /// Each bit represents a milestone, in the order of `STREAK_MILESTONES`
+    pub streak_bonuses_claimed: u8,
+    
+    /// Sponsor wallet that paid this account's rent and gets it back on close, or the default key
+    pub sponsored_by: Pubkey,
+    
+    /// When each module was started, by completing its first challenge, indexed by module id
+    /// Zero for modules that haven't been started, or were started before the account was migrated
+    pub module_started_at: [i64; MAX_MODULES],
+    
+    /// Zeroed space reserved for new fields, so they can be added without a realloc
+    pub reserved: [u8; UserProgress::RESERVED_BYTES],
+}
+
+impl UserProgress {
+    /// The layout version written by `initialize_user` and `migrate_user_progress`
+    pub const CURRENT_VERSION: u8 = 1;
+    
+    /// Bytes kept free at the end of the account for future fields
+    pub const RESERVED_BYTES: usize = 40;
+    
+    /// Calculate the space required for this account
+    pub const LEN: usize = 8 + // discriminator
+        1 +  // version (u8)
+        32 + // authority (Pubkey)
+        CHALLENGE_BITMAP_BYTES + // challenges_completed ([u8; 32])
+        1 +  // modules_completed (u8)
+        8 +  // created_at (i64)
+        8 +  // updated_at (i64)
+        1 +  // minted_modules (u8)
+        CHALLENGE_BITMAP_BYTES + // rewards_claimed ([u8; 32])
+        8 +  // xp (u64)
+        2 +  // level (u16)
+        2 +  // current_streak (u16)
+        2 +  // longest_streak (u16)
+        8 +  // last_active_day (i64)
+        1 +  // streak_bonuses_claimed (u8)
+        32 + // sponsored_by (Pubkey)
+        MAX_MODULES * 8 + // module_started_at ([i64; 8])
+        Self::RESERVED_BYTES; // reserved
+    
+    /// Check whether a challenge's bit is set
+    pub fn is_challenge_completed(&self, challenge_id: u8) -> bool {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.challenges_completed[byte] & mask != 0
+    }
+    
+    /// Set a challenge's bit
+    pub fn set_challenge_completed(&mut self, challenge_id: u8) {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.challenges_completed[byte] |= mask;
+    }
+    
+    /// Clear a challenge's bit
+    pub fn clear_challenge_completed(&mut self, challenge_id: u8) {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.challenges_completed[byte] &= !mask;
+    }
+    
+    /// Check whether a challenge's reward has been claimed
+    pub fn is_reward_claimed(&self, challenge_id: u8) -> bool {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.rewards_claimed[byte] & mask != 0
+    }
+    
+    /// Mark a challenge's reward as claimed
+    pub fn set_reward_claimed(&mut self, challenge_id: u8) {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.rewards_claimed[byte] |= mask;
+    }
+    
+    /// Add XP and recompute the level
+    pub fn award_xp(&mut self, xp: u64, curriculum: &Curriculum) {
+        self.xp = self.xp.saturating_add(xp);
+        self.refresh_level(curriculum);
+    }
+    
+    /// Take back XP and recompute the level
+    pub fn revoke_xp(&mut self, xp: u64, curriculum: &Curriculum) {
+        self.xp = self.xp.saturating_sub(xp);
+        self.refresh_level(curriculum);
+    }
+    
+    /// Recompute the level from the current XP curve, which may have changed since it was stored
+    pub fn refresh_level(&mut self, curriculum: &Curriculum) {
+        self.level = curriculum.level_for(self.xp);
+    }
+    
+    /// Record activity at `timestamp` for the daily streak
+    /// Returns the new streak when it just reached one of `STREAK_MILESTONES`
+    pub fn record_activity(&mut self, timestamp: i64) -> Option<u16> {
+        let day = timestamp.div_euclid(SECONDS_PER_DAY);
+        if self.current_streak > 0 && day == self.last_active_day {
+            return None;
+        }
+        
+        self.current_streak = if self.current_streak > 0 && day == self.last_active_day + 1 {
+            self.current_streak.saturating_add(1)
+        } else {
+            1
+        };
+        self.longest_streak = self.longest_streak.max(self.current_streak);
+        self.last_active_day = day;
+        
+        STREAK_MILESTONES.contains(&self.current_streak).then_some(self.current_streak)
+    }
+    
+    /// Check whether every prerequisite of a module has been completed
+    pub fn has_completed_prerequisites(&self, module: &ModuleConfig) -> bool {
+        module.prerequisites
+            .iter()
+            .all(|&prerequisite| self.modules_completed & (1u8 << prerequisite) != 0)
+    }
+}
+
+#[account]
+pub struct Sponsor {
+    /// The sponsor wallet, which signs as fee payer for the learner
+    pub payer: Pubkey,
+    
+    /// The sponsored learner
+    pub learner: Pubkey,
+    
+    /// Most lamports the sponsor will spend on this learner
+    pub budget_lamports: u64,
+    
+    /// Lamports spent on rent and fees for this learner so far
+    pub spent_lamports: u64,
+    
+    /// When the sponsorship was created
+    pub created_at: i64,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl Sponsor {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // payer (Pubkey)
+        32 + // learner (Pubkey)
+        8 +  // budget_lamports (u64)
+        8 +  // spent_lamports (u64)
+        8 +  // created_at (i64)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct UserTombstone {
+    /// The learner whose progress account was closed
+    pub authority: Pubkey,
+    
+    /// `UserProgress.minted_modules` at the time of closing
+    pub minted_modules: u8,
+    
+    /// `UserProgress.rewards_claimed` at the time of closing
+    pub rewards_claimed: [u8; CHALLENGE_BITMAP_BYTES],
+    
+    /// `UserProgress.streak_bonuses_claimed` at the time of closing
+    pub streak_bonuses_claimed: u8,
+    
+    /// When the progress account was last closed
+    pub closed_at: i64,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl UserTombstone {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority (Pubkey)
+        1 +  // minted_modules (u8)
+        CHALLENGE_BITMAP_BYTES + // rewards_claimed ([u8; 32])
+        1 +  // streak_bonuses_claimed (u8)
+        8 +  // closed_at (i64)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct TrackProgress {
+    /// Layout version of this account
+    pub version: u8,
+    
+    /// The public key of the user who owns this progress account
+    pub authority: Pubkey,
+    
+    /// The track this account records progress for
+    pub track_id: u8,
+    
+    /// Bitmap of the track's completed challenges, laid out like `UserProgress.challenges_completed`
+    pub challenges_completed: [u8; CHALLENGE_BITMAP_BYTES],
+    
+    /// Bitmask of the track's completed modules, laid out like `UserProgress.modules_completed`
+    pub modules_completed: u8,
+    
+    /// Timestamp when the account was created
+    pub created_at: i64,
+    
+    /// Timestamp when the account was last updated
+    pub updated_at: i64,
+    
+    /// PDA bump seed
+    pub bump: u8,
+    
+    /// Zeroed space reserved for new fields, so they can be added without a realloc
+    pub reserved: [u8; TrackProgress::RESERVED_BYTES],
+}
+
+impl TrackProgress {
+    /// The layout version written by `initialize_track`
+    pub const CURRENT_VERSION: u8 = 1;
+    
+    /// Bytes kept free at the end of the account for future fields
+    pub const RESERVED_BYTES: usize = 64;
+    
+    /// Calculate the space required for this account
+    pub const LEN: usize = 8 + // discriminator
+        1 +  // version (u8)
+        32 + // authority (Pubkey)
+        1 +  // track_id (u8)
+        CHALLENGE_BITMAP_BYTES + // challenges_completed ([u8; 32])
+        1 +  // modules_completed (u8)
+        8 +  // created_at (i64)
+        8 +  // updated_at (i64)
+        1 +  // bump (u8)
+        Self::RESERVED_BYTES; // reserved
+    
+    /// Check whether a challenge's bit is set
+    pub fn is_challenge_completed(&self, challenge_id: u8) -> bool {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.challenges_completed[byte] & mask != 0
+    }
+    
+    /// Set a challenge's bit
+    pub fn set_challenge_completed(&mut self, challenge_id: u8) {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.challenges_completed[byte] |= mask;
+    }
+    
+    /// Clear a challenge's bit
+    pub fn clear_challenge_completed(&mut self, challenge_id: u8) {
+        let (byte, mask) = challenge_bit(challenge_id);
+        self.challenges_completed[byte] &= !mask;
+    }
+}
+
+/// The original, unversioned `UserProgress` layout with a `u16` challenge bitmask
+/// Only used to read accounts that still need `migrate_user_progress`
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UserProgressV0 {
+    pub authority: Pubkey,
+    pub challenges_completed: u16,
+    pub modules_completed: u8,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl UserProgressV0 {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority (Pubkey)
+        2 +  // challenges_completed (u16)
+        1 +  // modules_completed (u8)
+        8 +  // created_at (i64)
+        8;   // updated_at (i64)
+}
+
+#[account]
+pub struct AchievementTemplate {
+    /// The module this template mints achievements for
+    pub module_id: u8,
+    
+    /// Metaplex name of minted NFTs
+    pub name: String,
+    
+    /// Metaplex symbol of minted NFTs
+    pub symbol: String,
+    
+    /// URI of the off-chain NFT JSON (image, description, attributes)
+    pub uri: String,
+    
+    /// Attributes mirrored on-chain so they can be read without fetching the URI
+    pub attributes: Vec<TemplateAttribute>,
+    
+    /// Whether minted NFTs are frozen in the learner's wallet (non-transferable)
+    pub soulbound: bool,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl AchievementTemplate {
+    pub const MAX_ATTRIBUTES: usize = 8;
+    
+    /// Calculate the space required for this account at its maximum size
+    pub const LEN: usize = 8 + // discriminator
+        1 + // module_id (u8)
+        4 + mpl_token_metadata::MAX_NAME_LENGTH +   // name (String)
+        4 + mpl_token_metadata::MAX_SYMBOL_LENGTH + // symbol (String)
+        4 + mpl_token_metadata::MAX_URI_LENGTH +    // uri (String)
+        4 + Self::MAX_ATTRIBUTES * TemplateAttribute::LEN + // attributes (Vec<TemplateAttribute>)
+        1 + // soulbound (bool)
+        1;  // bump (u8)
+    
+    /// Overwrite the template's metadata
+    fn apply(&mut self, data: AchievementTemplateData) {
+        self.name = data.name;
+        self.symbol = data.symbol;
+        self.uri = data.uri;
+        self.attributes = data.attributes;
+        self.soulbound = data.soulbound;
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TemplateAttribute {
+    pub trait_type: String,
+    pub value: String,
+}
+
+impl TemplateAttribute {
+    pub const MAX_TRAIT_TYPE_LEN: usize = 32;
+    pub const MAX_VALUE_LEN: usize = 32;
+    
+    /// Maximum serialized size of a single attribute
+    pub const LEN: usize = 4 + Self::MAX_TRAIT_TYPE_LEN + // trait_type (String)
+        4 + Self::MAX_VALUE_LEN; // value (String)
+}
+
+/// Instruction data for creating or updating an achievement template
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AchievementTemplateData {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub attributes: Vec<TemplateAttribute>,
+    pub soulbound: bool,
+}
+
+impl AchievementTemplateData {
+    /// Check the metadata against Metaplex limits and the template's reserved space
+    pub fn validate(&self) -> Result<()> {
+        validate_metadata_lengths(&self.name, &self.symbol, &self.uri)?;
+        require!(
+            self.attributes.len() <= AchievementTemplate::MAX_ATTRIBUTES,
+            ShadowRanchError::InvalidAttributes
+        );
+        require!(
+            self.attributes.iter().all(|attribute| {
+                attribute.trait_type.len() <= TemplateAttribute::MAX_TRAIT_TYPE_LEN
+                    && attribute.value.len() <= TemplateAttribute::MAX_VALUE_LEN
+            }),
+            ShadowRanchError::InvalidAttributes
+        );
+        Ok(())
+    }
+}
+
+#[account]
+pub struct RoleGrant {
+    /// The role held by `grantee`
+    pub role: Role,
+    
+    /// The key holding the role, e.g. the Ed25519 key a grading backend signs attestations with
+    pub grantee: Pubkey,
+    
+    /// The track an instructor manages, always 0 for other roles
+    pub track_id: u8,
+    
+    /// When the role was granted
+    pub granted_at: i64,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl RoleGrant {
+    pub const LEN: usize = 8 + // discriminator
+        1 +  // role (Role)
+        32 + // grantee (Pubkey)
+        1 +  // track_id (u8)
+        8 +  // granted_at (i64)
+        1;   // bump (u8)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Creates and updates modules for their own track, and can complete modules or mint for learners
+    Instructor,
+    
+    /// Attests challenge completions, and can complete modules for learners
+    Verifier,
+    
+    /// Revokes completions and achievements
+    Moderator,
+}
+
+#[account]
+pub struct RewardConfig {
+    /// RanchCoin paid out for each challenge, indexed by challenge id
+    pub challenge_rewards: Vec<u64>,
+    
+    /// RanchCoin paid out for reaching each of `STREAK_MILESTONES`
+    pub streak_bonuses: [u64; STREAK_MILESTONES.len()],
+    
+    /// Bump of the RanchCoin mint PDA, which signs for its own mints
+    pub mint_bump: u8,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl RewardConfig {
+    pub const LEN: usize = 8 + // discriminator
+        4 + MAX_CHALLENGES * 8 + // challenge_rewards (Vec<u64>)
+        STREAK_MILESTONES.len() * 8 + // streak_bonuses ([u64; 3])
+        1 +  // mint_bump (u8)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct Leaderboard {
+    /// `GLOBAL_LEADERBOARD` for the XP leaderboard, otherwise the module it ranks
+    pub leaderboard_id: u8,
+    
+    /// Maximum number of entries kept
+    pub capacity: u8,
+    
+    /// Entries in rank order, best first
+    pub entries: Vec<LeaderboardEntry>,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl Leaderboard {
+    /// Calculate the space required for a leaderboard holding `capacity` entries
+    pub const fn space(capacity: usize) -> usize {
+        8 +  // discriminator
+        1 +  // leaderboard_id (u8)
+        1 +  // capacity (u8)
+        4 +  // entries (Vec length prefix)
+        capacity * LeaderboardEntry::LEN +
+        1    // bump (u8)
+    }
+    
+    /// Whether `entry` ranks above `other`
+    /// The XP leaderboard ranks higher scores first, module leaderboards lower times first,
+    /// and equal scores go to whoever got there first
+    fn ranks_above(&self, entry: &LeaderboardEntry, other: &LeaderboardEntry) -> bool {
+        if entry.score == other.score {
+            return entry.timestamp < other.timestamp;
+        }
+        if self.leaderboard_id == GLOBAL_LEADERBOARD {
+            entry.score > other.score
+        } else {
+            entry.score < other.score
+        }
+    }
+    
+    /// Insert or replace a learner's entry, keeping only the best `capacity` entries
+    /// Resubmitting an unchanged score keeps its original timestamp, so the learner doesn't lose ties
+    pub fn submit(&mut self, authority: Pubkey, score: u64, timestamp: i64) {
+        let timestamp = self.entries
+            .iter()
+            .find(|entry| entry.authority == authority && entry.score == score)
+            .map_or(timestamp, |entry| entry.timestamp);
+        self.entries.retain(|entry| entry.authority != authority);
+        
+        let entry = LeaderboardEntry { authority, score, timestamp };
+        let rank = self.entries
+            .iter()
+            .position(|other| self.ranks_above(&entry, other))
+            .unwrap_or(self.entries.len());
+        if rank < self.capacity as usize {
+            self.entries.insert(rank, entry);
+            self.entries.truncate(self.capacity as usize);
+        }
+    }
+    
+    /// Replace the score of a learner already on the leaderboard, e.g. after XP is revoked
+    pub fn resubmit(&mut self, authority: Pubkey, score: u64, timestamp: i64) {
+        if self.entries.iter().any(|entry| entry.authority == authority) {
+            self.submit(authority, score, timestamp);
+        }
+    }
+    
+    /// Remove a learner's entry, if they have one
+    pub fn remove(&mut self, authority: &Pubkey) {
+        self.entries.retain(|entry| entry.authority != *authority);
+    }
+    
+    /// Submit a score unless the learner's existing entry already ranks above it
+    pub fn submit_best(&mut self, authority: Pubkey, score: u64, timestamp: i64) {
+        let entry = LeaderboardEntry { authority, score, timestamp };
+        let has_better = self.entries
+            .iter()
+            .any(|existing| existing.authority == authority && self.ranks_above(existing, &entry));
+        if !has_better {
+            self.submit(authority, score, timestamp);
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct LeaderboardEntry {
+    /// The ranked learner
+    pub authority: Pubkey,
+    
+    /// XP on the global leaderboard, seconds from the module's first challenge to its completion on module leaderboards
+    pub score: u64,
+    
+    /// When the score was submitted, used to break ties
+    pub timestamp: i64,
+}
+
+impl LeaderboardEntry {
+    pub const LEN: usize = 32 + // authority (Pubkey)
+        8 + // score (u64)
+        8;  // timestamp (i64)
+}
+
+#[account]
+pub struct RanchConfig {
+    /// RanchCoin prices of everything in the ranch shop
+    pub prices: RanchPrices,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl RanchConfig {
+    pub const LEN: usize = 8 + // discriminator
+        RanchPrices::LEN + // prices (RanchPrices)
+        1;   // bump (u8)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RanchPrices {
+    /// Price of a new building
+    pub building_price: u64,
+    
+    /// Price of a building upgrade or character level up, per current level
+    pub upgrade_price: u64,
+    
+    /// Price of a new character, indexed by `Rarity`
+    pub character_prices: [u64; Rarity::COUNT],
+}
+
+impl RanchPrices {
+    pub const LEN: usize = 8 + // building_price (u64)
+        8 + // upgrade_price (u64)
+        8 * Rarity::COUNT; // character_prices ([u64; 5])
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    pub const COUNT: usize = 5;
+}
+
+#[account]
+pub struct Ranch {
+    /// The learner who owns this ranch
+    pub authority: Pubkey,
+    
+    /// Number of buildings bought so far, and the id of the next one
+    pub building_count: u16,
+    
+    /// Number of characters recruited so far, and the id of the next one
+    pub character_count: u16,
+    
+    /// Timestamp when the ranch was created
+    pub created_at: i64,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl Ranch {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // authority (Pubkey)
+        2 +  // building_count (u16)
+        2 +  // character_count (u16)
+        8 +  // created_at (i64)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct Building {
+    /// The ranch this building stands on
+    pub ranch: Pubkey,
     
-    pub authority: Signer<'info>,
+    /// Id of the building within its ranch
+    pub id: u16,
+    
+    /// Building type, as defined by the frontend
+    pub kind: u8,
+    
+    /// Current level, starting at 1
+    pub level: u8,
+    
+    /// Position on the ranch, in percent
+    pub x: u16,
+    pub y: u16,
+    
+    /// Whether the building is shown on the ranch
+    pub is_active: bool,
+    
+    /// PDA bump seed
+    pub bump: u8,
 }
 
-#[derive(Accounts)]
-pub struct CompleteModule<'info> {
-    #[account(
-        mut,
-        seeds = [b"user_progress", authority.key().as_ref()],
-        bump,
-        has_one = authority
-    )]
-    pub user_progress: Account<'info, UserProgress>,
+impl Building {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // ranch (Pubkey)
+        2 +  // id (u16)
+        1 +  // kind (u8)
+        1 +  // level (u8)
+        2 +  // x (u16)
+        2 +  // y (u16)
+        1 +  // is_active (bool)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct Character {
+    /// The ranch this character lives on
+    pub ranch: Pubkey,
     
-    pub authority: Signer<'info>,
+    /// Id of the character within its ranch
+    pub id: u16,
+    
+    /// Character type, as defined by the frontend
+    pub kind: u8,
+    
+    /// Rarity the character was recruited at
+    pub rarity: Rarity,
+    
+    /// Current level, starting at 1
+    pub level: u8,
+    
+    /// Position on the ranch, in percent
+    pub x: u16,
+    pub y: u16,
+    
+    /// Whether the character is shown on the ranch
+    pub is_active: bool,
+    
+    /// PDA bump seed
+    pub bump: u8,
 }
 
-#[derive(Accounts)]
-pub struct MintAchievementNft<'info> {
-    #[account(
-        seeds = [b"user_progress", authority.key().as_ref()],
-        bump,
-        has_one = authority
-    )]
-    pub user_progress: Account<'info, UserProgress>,
+impl Character {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // ranch (Pubkey)
+        2 +  // id (u16)
+        1 +  // kind (u8)
+        1 +  // rarity (Rarity)
+        1 +  // level (u8)
+        2 +  // x (u16)
+        2 +  // y (u16)
+        1 +  // is_active (bool)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct AchievementRevocation {
+    /// The learner whose achievement was revoked
+    pub learner: Pubkey,
     
-    #[account(mut)]
-    pub mint: Account<'info, Mint>,
+    /// The module the revoked achievement was minted for
+    pub module_id: u8,
     
-    #[account(
-        init,
-        payer = payer,
-        mint = mint,
-        authority = authority,
-        associated_token_program = associated_token_program,
-        token_program = token_program,
-    )]
-    pub user_token_account: Account<'info, TokenAccount>,
+    /// Reason code given by the moderator
+    pub reason: u8,
     
-    #[account(
-        init,
-        payer = payer,
-        mint = mint,
-        update_authority = authority,
-        mint_authority = authority,
-        metadata = metadata,
-        token_program = token_program,
-        system_program = system_program,
-        rent = Some(rent.key()),
-    )]
-    pub master_edition: Account<'info, MasterEditionAccount>,
+    /// The admin or moderator who revoked it
+    pub revoked_by: Pubkey,
     
-    #[account(
-        init,
-        payer = payer,
-        mint = mint,
-        mint_authority = authority,
-        update_authority = authority,
-        metadata = metadata,
-        token_program = token_program,
-        system_program = system_program,
-        rent = Some(rent.key()),
-    )]
-    pub metadata: Account<'info, MetadataAccount>,
+    /// When the achievement was revoked
+    pub revoked_at: i64,
     
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl AchievementRevocation {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // learner (Pubkey)
+        1 +  // module_id (u8)
+        1 +  // reason (u8)
+        32 + // revoked_by (Pubkey)
+        8 +  // revoked_at (i64)
+        1;   // bump (u8)
+}
+
+#[account]
+pub struct AchievementTree {
+    /// The Merkle tree compressed achievements are minted into
+    pub merkle_tree: Pubkey,
     
-    pub authority: Signer<'info>,
+    /// Number of compressed achievements minted into the current tree
+    pub minted: u64,
     
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub metadata_program: Program<'info, Metadata>,
-    pub rent: Sysvar<'info, Rent>,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl AchievementTree {
+    pub const LEN: usize = 8 + // discriminator
+        32 + // merkle_tree (Pubkey)
+        8 +  // minted (u64)
+        1;   // bump (u8)
+}
+
+/// Bubblegum's `MetadataArgs`, serialized for `mint_to_collection_v1`
+/// Plain borsh rather than `AnchorSerialize`, as it is never part of this program's IDL
+#[derive(borsh::BorshSerialize)]
+struct CompressedMetadataArgs {
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    primary_sale_happened: bool,
+    is_mutable: bool,
+    edition_nonce: Option<u8>,
+    token_standard: Option<TokenStandard>,
+    collection: Option<Collection>,
+    uses: Option<Uses>,
+    token_program_version: u8,
+    creators: Vec<Creator>,
 }
 
 #[account]
-pub struct UserProgress {
-    /// The public key of the user who owns this progress account
-    pub authority: Pubkey,
+pub struct ProgramConfig {
+    /// The admin allowed to manage the curriculum, verifiers, achievements and this config
+    pub admin: Pubkey,
     
-    /// Bitmask tracking completion of up to 16 challenges
-    /// Each bit represents a challenge: bit 0 = challenge 0, bit 1 = challenge 1, etc.
-    pub challenges_completed: u16,
+    /// Admin proposed by `set_admin`, waiting to call `accept_admin`
+    pub pending_admin: Option<Pubkey>,
     
-    /// Bitmask tracking completion of up to 4 modules
-    /// Each bit represents a module: bit 0 = module 0, bit 1 = module 1, etc.
-    pub modules_completed: u8,
+    /// Whether state-changing instructions are currently blocked
+    pub paused: bool,
     
-    /// Timestamp when the account was created
-    pub created_at: i64,
+    /// Fee in lamports charged to the payer for every achievement mint
+    pub mint_fee_lamports: u64,
     
-    /// Timestamp when the account was last updated
-    pub updated_at: i64,
+    /// The account mint fees are paid to
+    pub treasury: Pubkey,
+    
+    /// Bitmask of the leaderboards created so far, see `leaderboard_bit`
+    pub leaderboards: u16,
+    
+    /// PDA bump seed
+    pub bump: u8,
 }
 
-impl UserProgress {
-    /// Calculate the space required for this account
+impl ProgramConfig {
     pub const LEN: usize = 8 + // discriminator
-        32 + // authority (Pubkey)
-        2 +  // challenges_completed (u16)
-        1 +  // modules_completed (u8)
-        8 +  // created_at (i64)
-        8;   // updated_at (i64)
+        32 + // admin (Pubkey)
+        33 + // pending_admin (Option<Pubkey>)
+        1 +  // paused (bool)
+        8 +  // mint_fee_lamports (u64)
+        32 + // treasury (Pubkey)
+        2 +  // leaderboards (u16)
+        1;   // bump (u8)
+    
+    /// Bit of `leaderboards` for a leaderboard
+    /// Module leaderboards use their module id, the global leaderboard the bit after the last module
+    pub fn leaderboard_bit(leaderboard_id: u8) -> u16 {
+        if leaderboard_id == GLOBAL_LEADERBOARD {
+            1 << MAX_MODULES
+        } else {
+            1 << leaderboard_id
+        }
+    }
+    
+    /// Check if a leaderboard has been created
+    pub fn has_leaderboard(&self, leaderboard_id: u8) -> bool {
+        (self.leaderboards & Self::leaderboard_bit(leaderboard_id)) != 0
+    }
+}
+
+#[account]
+pub struct Curriculum {
+    /// All registered learning modules
+    pub modules: Vec<ModuleConfig>,
+    
+    /// Whether challenges of modules with unmet prerequisites are locked too
+    pub strict_prerequisites: bool,
+    
+    /// XP needed to reach each level from level 2 on, in ascending order
+    pub level_thresholds: Vec<u64>,
+    
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+impl Curriculum {
+    pub const MAX_LEVEL_THRESHOLDS: usize = 64;
+    
+    /// Calculate the space required for a curriculum holding `module_count` modules
+    pub const fn space(module_count: usize) -> usize {
+        8 +  // discriminator
+        4 +  // modules (Vec length prefix)
+        module_count * ModuleConfig::LEN +
+        1 +  // strict_prerequisites (bool)
+        4 + Self::MAX_LEVEL_THRESHOLDS * 8 + // level_thresholds (Vec<u64>)
+        1    // bump (u8)
+    }
+    
+    /// The learner level for an amount of XP, level 1 until the first threshold is reached
+    pub fn level_for(&self, xp: u64) -> u16 {
+        1 + self.level_thresholds.iter().filter(|&&threshold| xp >= threshold).count() as u16
+    }
+    
+    /// XP earned for a set of completions, as `complete_challenge` and `complete_module` award it
+    pub fn xp_for(&self, challenges_completed: &[u8; CHALLENGE_BITMAP_BYTES], modules_completed: u8) -> u64 {
+        let challenge_xp: u64 = (0..=u8::MAX)
+            .filter(|&challenge_id| {
+                let (byte, mask) = challenge_bit(challenge_id);
+                challenges_completed[byte] & mask != 0
+            })
+            .filter_map(|challenge_id| self.module_of_challenge(challenge_id))
+            .map(|module| module.challenge_xp as u64)
+            .sum();
+        let module_xp: u64 = self.modules
+            .iter()
+            .filter(|module| modules_completed & (1u8 << module.module_id) != 0)
+            .map(|module| module.module_xp as u64)
+            .sum();
+        challenge_xp + module_xp
+    }
+    
+    /// Find a module by its id
+    pub fn find_module(&self, module_id: u8) -> Option<&ModuleConfig> {
+        self.modules.iter().find(|module| module.module_id == module_id)
+    }
+    
+    /// Check that every prerequisite of `module` is registered and doesn't itself depend on `module`
+    /// `module` stands in for any registered entry with the same id, so updates can't close a cycle
+    pub fn validate_prerequisites(&self, module: &ModuleConfig) -> Result<()> {
+        let mut pending = module.prerequisites.clone();
+        let mut visited = 0u8;
+        
+        while let Some(id) = pending.pop() {
+            require!(id != module.module_id, ShadowRanchError::InvalidPrerequisites);
+            if visited & (1u8 << id) != 0 {
+                continue;
+            }
+            visited |= 1u8 << id;
+            
+            let prerequisite = self.find_module(id).ok_or(ShadowRanchError::InvalidPrerequisites)?;
+            pending.extend_from_slice(&prerequisite.prerequisites);
+        }
+        Ok(())
+    }
+    
+    /// The track a module belongs to, defaulting to track 0 for unknown modules
+    pub fn track_of(&self, module_id: u8) -> u8 {
+        self.find_module(module_id).map_or(0, |module| module.track_id)
+    }
+    
+    /// Find the module a challenge belongs to
+    pub fn module_of_challenge(&self, challenge_id: u8) -> Option<&ModuleConfig> {
+        self.modules.iter().find(|module| module.challenge_ids.contains(&challenge_id))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ModuleConfig {
+    /// The module id, used as the bit index in `UserProgress.modules_completed`
+    pub module_id: u8,
+    
+    /// The track this module belongs to, which selects its achievement collection
+    pub track_id: u8,
+    
+    /// Challenges that must all be completed to finish this module
+    pub challenge_ids: Vec<u8>,
+    
+    /// Modules that should be completed before this one
+    pub prerequisites: Vec<u8>,
+    
+    /// XP awarded for each of the module's challenges
+    pub challenge_xp: u32,
+    
+    /// XP awarded for completing the whole module
+    pub module_xp: u32,
+    
+    /// Display title shown in the frontend
+    pub title: String,
+    
+    /// URI pointing to the off-chain lesson metadata
+    pub uri: String,
+}
+
+impl ModuleConfig {
+    pub const MAX_CHALLENGES: usize = 16;
+    pub const MAX_PREREQUISITES: usize = 8;
+    pub const MAX_TITLE_LEN: usize = 64;
+    pub const MAX_URI_LEN: usize = 200;
+    
+    /// Maximum serialized size of a single module entry
+    pub const LEN: usize = 1 + // module_id (u8)
+        1 + // track_id (u8)
+        4 + Self::MAX_CHALLENGES +    // challenge_ids (Vec<u8>)
+        4 + Self::MAX_PREREQUISITES + // prerequisites (Vec<u8>)
+        4 + // challenge_xp (u32)
+        4 + // module_xp (u32)
+        4 + Self::MAX_TITLE_LEN +     // title (String)
+        4 + Self::MAX_URI_LEN;        // uri (String)
+    
+    /// Check that the module fits in its reserved space and only references valid ids
+    pub fn validate(&self) -> Result<()> {
+        require!((self.module_id as usize) < MAX_MODULES, ShadowRanchError::InvalidModuleId);
+        require!((self.track_id as usize) < MAX_TRACKS, ShadowRanchError::InvalidTrackId);
+        require!(
+            !self.challenge_ids.is_empty() && self.challenge_ids.len() <= Self::MAX_CHALLENGES,
+            ShadowRanchError::InvalidModuleChallenges
+        );
+        require!(
+            self.challenge_ids.iter().all(|&id| (id as usize) < MAX_CHALLENGES),
+            ShadowRanchError::InvalidChallengeId
+        );
+        require!(
+            self.prerequisites.len() <= Self::MAX_PREREQUISITES,
+            ShadowRanchError::InvalidPrerequisites
+        );
+        require!(
+            self.prerequisites
+                .iter()
+                .all(|&id| (id as usize) < MAX_MODULES && id != self.module_id),
+            ShadowRanchError::InvalidPrerequisites
+        );
+        require!(self.title.len() <= Self::MAX_TITLE_LEN, ShadowRanchError::TitleTooLong);
+        require!(self.uri.len() <= Self::MAX_URI_LEN, ShadowRanchError::UriTooLong);
+        Ok(())
+    }
+}
+
+/// Emitted when a learner's progress account is created
+#[event]
+pub struct UserInitialized {
+    pub authority: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted when a challenge is completed, with the learner's updated challenge bitmap and XP
+#[event]
+pub struct ChallengeCompleted {
+    pub authority: Pubkey,
+    pub challenge_id: u8,
+    pub challenges_completed: [u8; CHALLENGE_BITMAP_BYTES],
+    pub xp: u64,
+    pub level: u16,
+    pub timestamp: i64,
+}
+
+/// Emitted when a module is completed, with the learner's updated module bitmap and XP
+#[event]
+pub struct ModuleCompleted {
+    pub authority: Pubkey,
+    pub module_id: u8,
+    pub modules_completed: u8,
+    pub xp: u64,
+    pub level: u16,
+    pub timestamp: i64,
+}
+
+/// Emitted when an achievement is minted, with the learner's updated minted bitmap
+#[event]
+pub struct AchievementMinted {
+    pub authority: Pubkey,
+    pub module_id: u8,
+    
+    /// The achievement mint, or the Bubblegum asset id for compressed achievements
+    pub asset: Pubkey,
+    
+    pub minted_modules: u8,
+    pub timestamp: i64,
+}
+
+/// Emitted when a learner's daily streak reaches one of `STREAK_MILESTONES`
+#[event]
+pub struct StreakMilestone {
+    pub authority: Pubkey,
+    pub streak: u16,
+    pub timestamp: i64,
+}
+
+/// Emitted when a moderator revokes a challenge completion
+#[event]
+pub struct ChallengeRevoked {
+    pub learner: Pubkey,
+    pub challenge_id: u8,
+    pub reason: u8,
+    pub revoked_by: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted when a moderator revokes a module completion
+#[event]
+pub struct ModuleRevoked {
+    pub learner: Pubkey,
+    pub module_id: u8,
+    pub reason: u8,
+    pub revoked_by: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Emitted when a moderator revokes a minted achievement
+#[event]
+pub struct AchievementRevoked {
+    pub learner: Pubkey,
+    pub module_id: u8,
+    pub reason: u8,
+    pub revoked_by: Pubkey,
+    pub timestamp: i64,
+}
+
+/// Helper function to check NFT metadata against the Metaplex length limits
+fn validate_metadata_lengths(name: &str, symbol: &str, uri: &str) -> Result<()> {
+    require!(name.len() <= mpl_token_metadata::MAX_NAME_LENGTH, ShadowRanchError::NameTooLong);
+    require!(symbol.len() <= mpl_token_metadata::MAX_SYMBOL_LENGTH, ShadowRanchError::SymbolTooLong);
+    require!(uri.len() <= mpl_token_metadata::MAX_URI_LENGTH, ShadowRanchError::UriTooLong);
+    Ok(())
+}
+
+/// Helper function to locate a challenge's byte index and bit mask in the bitmap
+fn challenge_bit(challenge_id: u8) -> (usize, u8) {
+    ((challenge_id / 8) as usize, 1u8 << (challenge_id % 8))
+}
+
+/// Helper function to build the message a verifier signs to attest a challenge completion
+/// Layout: `ATTESTATION_DOMAIN` | program id (32 bytes) | learner (32 bytes) | challenge_id (1 byte) |
+/// expiry (i64 LE)
+pub fn challenge_attestation_message(learner: &Pubkey, challenge_id: u8, expiry: i64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ATTESTATION_DOMAIN.len() + 32 + 32 + 1 + 8);
+    message.extend_from_slice(ATTESTATION_DOMAIN);
+    message.extend_from_slice(crate::ID.as_ref());
+    message.extend_from_slice(learner.as_ref());
+    message.push(challenge_id);
+    message.extend_from_slice(&expiry.to_le_bytes());
+    message
+}
+
+/// Helper function to check that an instruction is an Ed25519 program signature check
+/// by `signer` over `message`, with all data embedded in the instruction itself
+fn verify_ed25519_instruction(ix: &Instruction, signer: &Pubkey, message: &[u8]) -> Result<()> {
+    require!(
+        ix.program_id == ed25519_program::ID && ix.accounts.is_empty(),
+        ShadowRanchError::InvalidAttestation
+    );
+    
+    // Header: signature count (u8), padding (u8), then one set of offsets
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ShadowRanchError::InvalidAttestation);
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]) as usize;
+    let signature_ix_index = read_u16(4);
+    let public_key_offset = read_u16(6);
+    let public_key_ix_index = read_u16(8);
+    let message_offset = read_u16(10);
+    let message_size = read_u16(12);
+    let message_ix_index = read_u16(14);
+    
+    // The signature, key and message must all live in the Ed25519 instruction
+    let this_ix = u16::MAX as usize;
+    require!(
+        signature_ix_index == this_ix && public_key_ix_index == this_ix && message_ix_index == this_ix,
+        ShadowRanchError::InvalidAttestation
+    );
+    
+    let signed_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(ShadowRanchError::InvalidAttestation)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(ShadowRanchError::InvalidAttestation)?;
+    require!(
+        signed_key == signer.as_ref() && signed_message == message,
+        ShadowRanchError::InvalidAttestation
+    );
+    Ok(())
+}
+
+/// Helper function to check a role grant belongs to `signer` and is one of `roles`
+/// Instructor grants only count for their own track
+fn require_role(
+    signer: &Pubkey,
+    role_grant: Option<&RoleGrant>,
+    roles: &[Role],
+    track_id: u8,
+) -> Result<()> {
+    match role_grant {
+        Some(grant)
+            if grant.grantee == *signer
+                && roles.contains(&grant.role)
+                && (grant.role != Role::Instructor || grant.track_id == track_id) =>
+        {
+            Ok(())
+        }
+        _ => err!(ShadowRanchError::Unauthorized),
+    }
+}
+
+/// Helper function to check the signer is the learner, or holds one of `roles`
+fn require_learner_or_role(
+    signer: &Pubkey,
+    learner: &Pubkey,
+    role_grant: Option<&RoleGrant>,
+    roles: &[Role],
+    track_id: u8,
+) -> Result<()> {
+    if signer == learner {
+        return Ok(());
+    }
+    require_role(signer, role_grant, roles, track_id)
+}
+
+/// Helper function to check the signer is the program admin, or an instructor for `track_id`
+fn require_admin_or_instructor(
+    config: &ProgramConfig,
+    signer: &Pubkey,
+    role_grant: Option<&RoleGrant>,
+    track_id: u8,
+) -> Result<()> {
+    if *signer == config.admin {
+        return Ok(());
+    }
+    require_role(signer, role_grant, &[Role::Instructor], track_id)
+}
+
+/// Helper function to check the signer is the program admin, or a moderator
+fn require_admin_or_moderator(
+    config: &ProgramConfig,
+    signer: &Pubkey,
+    role_grant: Option<&RoleGrant>,
+) -> Result<()> {
+    if *signer == config.admin {
+        return Ok(());
+    }
+    require_role(signer, role_grant, &[Role::Moderator], 0)
+}
+
+/// Helper function to check a leaderboard that has been created was passed,
+/// so learners can't skip being ranked and moderators can't skip removing revoked entries
+fn require_leaderboard(config: &ProgramConfig, leaderboard_id: u8, leaderboard: Option<&Leaderboard>) -> Result<()> {
+    require!(
+        leaderboard.is_some() || !config.has_leaderboard(leaderboard_id),
+        ShadowRanchError::LeaderboardRequired
+    );
+    Ok(())
+}
+
+/// Helper function to charge lamports a payer spent against their sponsorship
+/// Without a sponsorship, any payer may cover the learner's costs without a budget
+fn charge_sponsor(sponsor: Option<&mut Sponsor>, lamports: u64) -> Result<()> {
+    let Some(sponsor) = sponsor else {
+        return Ok(());
+    };
+    
+    sponsor.spent_lamports = sponsor.spent_lamports.saturating_add(lamports);
+    require!(
+        sponsor.spent_lamports <= sponsor.budget_lamports,
+        ShadowRanchError::SponsorBudgetExceeded
+    );
+    Ok(())
+}
+
+/// Helper function to mint a RanchCoin reward into the learner's associated token account
+/// The token account is created on the learner's first claim, with its rent charged to any sponsorship
+fn mint_ranch_coin_reward(accounts: &mut ClaimReward, amount: u64) -> Result<()> {
+    let payer_lamports = accounts.payer.lamports();
+    let cpi_accounts = associated_token::Create {
+        payer: accounts.payer.to_account_info(),
+        associated_token: accounts.learner_token_account.to_account_info(),
+        authority: accounts.authority.to_account_info(),
+        mint: accounts.ranch_coin_mint.to_account_info(),
+        system_program: accounts.system_program.to_account_info(),
+        token_program: accounts.token_program.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(accounts.associated_token_program.to_account_info(), cpi_accounts);
+    associated_token::create_idempotent(cpi_ctx)?;
+    
+    // The mint is its own authority, so only this program can issue RanchCoin
+    let ranch_coin_mint_seeds: &[&[&[u8]]] = &[&[
+        b"ranch_coin_mint",
+        &[accounts.reward_config.mint_bump],
+    ]];
+    let cpi_accounts = MintTo {
+        mint: accounts.ranch_coin_mint.to_account_info(),
+        to: accounts.learner_token_account.to_account_info(),
+        authority: accounts.ranch_coin_mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        cpi_accounts,
+        ranch_coin_mint_seeds,
+    );
+    mint_to(cpi_ctx, amount)?;
+    
+    let spent = payer_lamports - accounts.payer.lamports();
+    charge_sponsor(
+        accounts.sponsor.as_deref_mut(),
+        spent,
+    )
+}
+
+/// Helper function to record activity for the daily streak, emitting an event at milestones
+fn record_streak(user_progress: &mut UserProgress, timestamp: i64) {
+    if let Some(streak) = user_progress.record_activity(timestamp) {
+        emit!(StreakMilestone {
+            authority: user_progress.authority,
+            streak,
+            timestamp,
+        });
+    }
 }
 
-/// Helper function to get the challenges for a specific module
-fn get_module_challenges(module_id: u8) -> Vec<u8> {
-    match module_id {
-        0 => vec![0, 1, 2, 3],     // Module 0: Challenges 0-3
-        1 => vec![4, 5, 6, 7],     // Module 1: Challenges 4-7
-        2 => vec![8, 9, 10, 11],   // Module 2: Challenges 8-11
-        3 => vec![12, 13, 14, 15], // Module 3: Challenges 12-15
-        _ => vec![],                // Invalid module
+/// Helper function to check a position lies on the ranch
+fn validate_position(x: u16, y: u16) -> Result<()> {
+    require!(
+        x <= MAX_RANCH_POSITION && y <= MAX_RANCH_POSITION,
+        ShadowRanchError::InvalidPosition
+    );
+    Ok(())
+}
+
+/// Helper function to pay for something in the ranch shop by burning the learner's RanchCoin
+fn burn_ranch_coin<'info>(
+    token_program: &Program<'info, Token>,
+    mint: &Account<'info, Mint>,
+    from: &Account<'info, TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<()> {
+    burn(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Burn {
+                mint: mint.to_account_info(),
+                from: from.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+/// Helper function to charge the configured mint fee, if any, from `payer` to the treasury
+fn charge_mint_fee<'info>(
+    config: &ProgramConfig,
+    payer: &AccountInfo<'info>,
+    treasury: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    if config.mint_fee_lamports == 0 {
+        return Ok(());
+    }
+    
+    system_program::transfer(
+        CpiContext::new(
+            system_program.clone(),
+            system_program::Transfer {
+                from: payer.clone(),
+                to: treasury.clone(),
+            },
+        ),
+        config.mint_fee_lamports,
+    )
+}
+
+/// Helper function to create an account at a PDA, the way Anchor's `init` does
+/// `create_account` fails once the address holds any lamports, so a funded address is
+/// topped up, allocated and assigned instead, and can't be blocked by a stray transfer
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    lamports: u64,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let current_lamports = account.lamports();
+    if current_lamports == 0 {
+        let cpi_accounts = system_program::CreateAccount {
+            from: payer.clone(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(system_program.clone(), cpi_accounts, signer_seeds);
+        return system_program::create_account(cpi_ctx, lamports, space as u64, owner);
+    }
+    
+    if lamports > current_lamports {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.clone(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.clone(), cpi_accounts);
+        system_program::transfer(cpi_ctx, lamports - current_lamports)?;
     }
+    
+    let cpi_accounts = system_program::Allocate {
+        account_to_allocate: account.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(system_program.clone(), cpi_accounts, signer_seeds);
+    system_program::allocate(cpi_ctx, space as u64)?;
+    
+    let cpi_accounts = system_program::Assign {
+        account_to_assign: account.clone(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(system_program.clone(), cpi_accounts, signer_seeds);
+    system_program::assign(cpi_ctx, owner)
 }
 
-/// Helper function to create a bitmask for a module's challenges
-fn create_module_mask(challenges: Vec<u8>) -> u16 {
-    challenges.iter().fold(0u16, |acc, &challenge_id| {
-        acc | (1u16 << challenge_id)
-    })
+/// Helper function to grow or shrink a program-owned account, topping up rent from `payer`
+fn resize_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    new_len: usize,
+) -> Result<()> {
+    let required_lamports = Rent::get()?.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+    
+    if required_lamports > current_lamports {
+        let cpi_accounts = system_program::Transfer {
+            from: payer.clone(),
+            to: account.clone(),
+        };
+        let cpi_ctx = CpiContext::new(system_program.clone(), cpi_accounts);
+        system_program::transfer(cpi_ctx, required_lamports - current_lamports)?;
+    }
+    
+    account.realloc(new_len, false)?;
+    Ok(())
 }
 
 #[error_code]
 pub enum ShadowRanchError {
-    #[msg("Invalid challenge ID. Must be between 0 and 15.")]
+    #[msg("Invalid challenge ID. Must be between 0 and 255.")]
     InvalidChallengeId,
     
-    #[msg("Invalid module ID. Must be between 0 and 3.")]
+    #[msg("Invalid module ID. The module is not registered in the curriculum.")]
     InvalidModuleId,
     
     #[msg("Unauthorized. Only the account authority can perform this action.")]
@@ -389,4 +5026,94 @@ pub enum ShadowRanchError {
     
     #[msg("Module not complete. All challenges in the module must be completed first.")]
     ModuleNotComplete,
+    
+    #[msg("A module with this ID already exists in the curriculum.")]
+    ModuleAlreadyExists,
+    
+    #[msg("A module must list between 1 and 16 challenges.")]
+    InvalidModuleChallenges,
+    
+    #[msg("Invalid prerequisites. At most 8 existing modules other than the module itself.")]
+    InvalidPrerequisites,
+    
+    #[msg("Title is too long.")]
+    TitleTooLong,
+    
+    #[msg("URI is too long.")]
+    UriTooLong,
+    
+    #[msg("This account is already on the current layout and does not need migrating.")]
+    AlreadyMigrated,
+    
+    #[msg("Missing or invalid verifier attestation for this challenge.")]
+    InvalidAttestation,
+    
+    #[msg("The verifier attestation has expired.")]
+    AttestationExpired,
+    
+    #[msg("The verifier attestation is for a challenge that has already been completed.")]
+    AttestationReplayed,
+    
+    #[msg("Invalid track ID. Must be between 0 and 7.")]
+    InvalidTrackId,
+    
+    #[msg("The achievement NFT for this module has already been minted.")]
+    AchievementAlreadyMinted,
+    
+    #[msg("Name is too long. Metaplex allows at most 32 bytes.")]
+    NameTooLong,
+    
+    #[msg("Symbol is too long. Metaplex allows at most 10 bytes.")]
+    SymbolTooLong,
+    
+    #[msg("Invalid attributes. At most 8 attributes with 32-byte trait types and values.")]
+    InvalidAttributes,
+    
+    #[msg("Soulbound achievements can't be minted as compressed NFTs.")]
+    SoulboundNotSupported,
+    
+    #[msg("The program is paused.")]
+    ProgramPaused,
+    
+    #[msg("This module's prerequisites haven't been completed yet.")]
+    PrerequisiteNotMet,
+    
+    #[msg("This challenge hasn't been completed.")]
+    ChallengeNotCompleted,
+    
+    #[msg("No achievement has been minted for this module.")]
+    AchievementNotMinted,
+    
+    #[msg("This transaction would exceed the learner's sponsorship budget.")]
+    SponsorBudgetExceeded,
+    
+    #[msg("The reward for this challenge has already been claimed.")]
+    RewardAlreadyClaimed,
+    
+    #[msg("Position is outside the ranch.")]
+    InvalidPosition,
+    
+    #[msg("This building or character is already at the maximum level.")]
+    MaxLevelReached,
+    
+    #[msg("Exactly one building or character must be passed.")]
+    InvalidEntity,
+    
+    #[msg("Invalid XP curve. At most 64 strictly increasing thresholds.")]
+    InvalidXpCurve,
+    
+    #[msg("No unclaimed streak milestone with a bonus has been reached.")]
+    NoStreakBonus,
+    
+    #[msg("Invalid leaderboard capacity. Must be between 1 and 100.")]
+    InvalidLeaderboardCapacity,
+    
+    #[msg("The rent of a sponsored account must be returned to the sponsor.")]
+    SponsorRefundRequired,
+    
+    #[msg("The ranch can't hold any more buildings or characters.")]
+    RanchFull,
+    
+    #[msg("This leaderboard exists and must be passed.")]
+    LeaderboardRequired,
 }
@@ -0,0 +1,14 @@
+#!/usr/bin/env sh
+# Dumps the mainnet programs the tests load into tests/fixtures, skipping any already there
+set -e
+
+dump() {
+  if [ ! -f "tests/fixtures/$2.so" ]; then
+    solana program dump -u m "$1" "tests/fixtures/$2.so"
+  fi
+}
+
+dump metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s mpl_token_metadata
+dump BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY mpl_bubblegum
+dump cmtKgWPEyuAnGhCrNgFRQSMT9E4Dp4uErXqWs5rNo1D spl_account_compression
+dump noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV spl_noop
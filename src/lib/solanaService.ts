@@ -13,8 +13,9 @@ import {
   PublicKey,
   SystemProgram,
   SYSVAR_RENT_PUBKEY,
-  Keypair,
   Transaction,
+  Ed25519Program,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
 } from '@solana/web3.js';
 import {
   Program,
@@ -38,23 +39,50 @@ export const TOKEN_METADATA_PROGRAM_ID = new PublicKey('metaqbxxUerdq28cj1RbAWkY
 
 // PDA seed constants
 const USER_PROGRESS_SEED = 'user_progress';
+const CONFIG_SEED = 'config';
+const USER_TOMBSTONE_SEED = 'user_tombstone';
+const CURRICULUM_SEED = 'curriculum';
+const ROLE_GRANT_SEED = 'role_grant';
+const TRACK_PROGRESS_SEED = 'track_progress';
+const ACHIEVEMENT_AUTHORITY_SEED = 'achievement_authority';
+const ACHIEVEMENT_MINT_SEED = 'achievement_mint';
+const ACHIEVEMENT_TEMPLATE_SEED = 'achievement_template';
+const COLLECTION_MINT_SEED = 'collection_mint';
+const LEADERBOARD_SEED = 'leaderboard';
+
+// Leaderboard id of the global XP leaderboard, mirrors `GLOBAL_LEADERBOARD` in the program
+const GLOBAL_LEADERBOARD = 255;
+
+// Domain tag prepended to every verifier attestation, mirrors `ATTESTATION_DOMAIN` in the program
+const ATTESTATION_DOMAIN = 'shadow-ranch:challenge-attestation:v1';
+
+// Challenge ids are a u8 indexing the 256-bit completion bitmap
+const MAX_CHALLENGES = 256;
+
+// Module ids index the 8-bit module completion bitmask
+const MAX_MODULES = 8;
+
+// Role discriminants, in the order of the program's `Role` enum
+const VERIFIER_ROLE = 1;
 
 /**
  * Type definitions for the service
  */
 export interface UserProgress {
   authority: PublicKey;
-  challengesCompleted: number;
+  challengesCompleted: number[];
   modulesCompleted: number;
   createdAt: BN;
   updatedAt: BN;
 }
 
-export interface NFTMetadata {
-  title: string;
-  symbol: string;
-  uri: string;
-  moduleId: number;
+export interface ChallengeAttestation {
+  /** The verifier that signed the attestation */
+  verifier: PublicKey;
+  /** Unix timestamp after which the attestation is rejected */
+  expiry: BN;
+  /** The verifier's Ed25519 signature over `buildAttestationMessage` */
+  signature: Uint8Array;
 }
 
 export interface WalletAdapter {
@@ -110,6 +138,143 @@ export class SolanaService {
     );
   }
 
+  /**
+   * Find the tombstone PDA left behind when the user closes their progress account
+   * @param userPublicKey - The user's public key
+   * @returns The PDA and bump seed
+   */
+  findUserTombstonePDA(userPublicKey: PublicKey): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(USER_TOMBSTONE_SEED), userPublicKey.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Find the global program config PDA, read by every state-changing instruction
+   * @returns The PDA and bump seed
+   */
+  findConfigPDA(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from(CONFIG_SEED)], this.programId);
+  }
+
+  /**
+   * Find the curriculum PDA holding the registered modules
+   * @returns The PDA and bump seed
+   */
+  findCurriculumPDA(): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync([Buffer.from(CURRICULUM_SEED)], this.programId);
+  }
+
+  /**
+   * Find a role grant PDA, only instructor grants are scoped to a track
+   * @param role - The role discriminant
+   * @param grantee - The public key holding the role
+   * @param trackId - The track the role is granted for, 0 for non-instructor roles
+   * @returns The PDA and bump seed
+   */
+  findRoleGrantPDA(role: number, grantee: PublicKey, trackId: number = 0): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(ROLE_GRANT_SEED), Buffer.from([role]), Buffer.from([trackId]), grantee.toBuffer()],
+      this.programId
+    );
+  }
+
+  /**
+   * Find the user's progress PDA for a track
+   * @param userPublicKey - The user's public key
+   * @param trackId - The track ID
+   * @returns The PDA and bump seed
+   */
+  findTrackProgressPDA(userPublicKey: PublicKey, trackId: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(TRACK_PROGRESS_SEED), userPublicKey.toBuffer(), Buffer.from([trackId])],
+      this.programId
+    );
+  }
+
+  /**
+   * Find the Token Metadata PDA of a mint, or its master edition
+   * @param mint - The mint address
+   * @param edition - Whether to derive the master edition instead of the metadata
+   * @returns The PDA and bump seed
+   */
+  findMetadataPDA(mint: PublicKey, edition: boolean = false): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from('metadata'),
+        TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+        mint.toBuffer(),
+        ...(edition ? [Buffer.from('edition')] : []),
+      ],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+  }
+
+  /**
+   * Find the user's achievement mint PDA for a module
+   * @param userPublicKey - The user's public key
+   * @param moduleId - The module ID
+   * @returns The PDA and bump seed
+   */
+  findAchievementMintPDA(userPublicKey: PublicKey, moduleId: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(ACHIEVEMENT_MINT_SEED), userPublicKey.toBuffer(), Buffer.from([moduleId])],
+      this.programId
+    );
+  }
+
+  /**
+   * Find a leaderboard PDA
+   * @param leaderboardId - The module ID, or `GLOBAL_LEADERBOARD` for the global one
+   * @returns The PDA and bump seed
+   */
+  findLeaderboardPDA(leaderboardId: number): [PublicKey, number] {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from(LEADERBOARD_SEED), Buffer.from([leaderboardId])],
+      this.programId
+    );
+  }
+
+  /**
+   * Build the message a verifier signs to attest a challenge completion
+   * Mirrors `challenge_attestation_message` in the program
+   * @param learner - The learner completing the challenge
+   * @param challengeId - The ID of the completed challenge
+   * @param expiry - Unix timestamp after which the attestation is rejected
+   * @returns The message bytes
+   */
+  buildAttestationMessage(learner: PublicKey, challengeId: number, expiry: BN): Buffer {
+    return Buffer.concat([
+      Buffer.from(ATTESTATION_DOMAIN),
+      this.programId.toBuffer(),
+      learner.toBuffer(),
+      Buffer.from([challengeId]),
+      expiry.toArrayLike(Buffer, 'le', 8),
+    ]);
+  }
+
+  /**
+   * Get the user's progress PDA for a track, or null if they haven't initialized it
+   * @param program - The Anchor program instance
+   * @param userPublicKey - The user's public key
+   * @param trackId - The track ID, or undefined if the item isn't in the curriculum
+   * @returns The PDA or null
+   */
+  private async findInitializedTrackProgress(
+    program: Program<ShadowRanchProgram>,
+    userPublicKey: PublicKey,
+    trackId: number | undefined
+  ): Promise<PublicKey | null> {
+    if (trackId === undefined) {
+      return null;
+    }
+
+    const [trackProgressPDA] = this.findTrackProgressPDA(userPublicKey, trackId);
+    const account = await program.provider.connection.getAccountInfo(trackProgressPDA);
+    return account ? trackProgressPDA : null;
+  }
+
   /**
    * Get the user's progress account data
    * @param wallet - The wallet adapter instance
@@ -159,8 +324,11 @@ export class SolanaService {
       const txSignature = await program.methods
         .initializeUser()
         .accounts({
+          config: this.findConfigPDA()[0],
           userProgress: userProgressPDA,
+          tombstone: this.findUserTombstonePDA(wallet.publicKey)[0],
           payer: wallet.publicKey,
+          sponsor: null,
           authority: wallet.publicKey,
           systemProgram: SystemProgram.programId,
         })
@@ -175,31 +343,59 @@ export class SolanaService {
   }
 
   /**
-   * Complete a specific challenge
+   * Complete a specific challenge with an attestation signed by a verifier
    * @param wallet - The wallet adapter instance
-   * @param challengeId - The ID of the challenge to complete (0-15)
+   * @param challengeId - The ID of the challenge to complete (0-255)
+   * @param attestation - The verifier's signed attestation of the completion
    * @returns Transaction signature
    */
-  async completeChallenge(wallet: WalletAdapter, challengeId: number): Promise<string> {
+  async completeChallenge(
+    wallet: WalletAdapter,
+    challengeId: number,
+    attestation: ChallengeAttestation
+  ): Promise<string> {
     if (!wallet.publicKey) {
       throw new Error('Wallet not connected');
     }
 
-    if (challengeId < 0 || challengeId > 15) {
-      throw new Error('Invalid challenge ID. Must be between 0 and 15.');
+    if (challengeId < 0 || challengeId >= MAX_CHALLENGES) {
+      throw new Error(`Invalid challenge ID. Must be between 0 and ${MAX_CHALLENGES - 1}.`);
     }
 
     const program = this.getProgram(wallet);
     const [userProgressPDA] = await this.findUserProgressPDA(wallet.publicKey);
+    const [curriculumPDA] = this.findCurriculumPDA();
 
     try {
+      // Record the completion in the learner's track progress too, if they started the track
+      const curriculum = await program.account.curriculum.fetch(curriculumPDA);
+      const challengeModule = curriculum.modules.find((m) => m.challengeIds.includes(challengeId));
+      const trackProgress = await this.findInitializedTrackProgress(
+        program,
+        wallet.publicKey,
+        challengeModule?.trackId
+      );
+
+      // The program checks the verifier's signature in the instruction right before its own
+      const ed25519Instruction = Ed25519Program.createInstructionWithPublicKey({
+        publicKey: attestation.verifier.toBytes(),
+        message: this.buildAttestationMessage(wallet.publicKey, challengeId, attestation.expiry),
+        signature: attestation.signature,
+      });
+
       // Build and send the complete_challenge transaction
       const txSignature = await program.methods
-        .completeChallenge(challengeId)
+        .completeChallenge(challengeId, attestation.expiry)
         .accounts({
+          config: this.findConfigPDA()[0],
           userProgress: userProgressPDA,
+          verifier: this.findRoleGrantPDA(VERIFIER_ROLE, attestation.verifier)[0],
+          curriculum: curriculumPDA,
+          trackProgress,
           authority: wallet.publicKey,
+          instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
         })
+        .preInstructions([ed25519Instruction])
         .rpc();
 
       console.log(`Challenge ${challengeId} completed successfully:`, txSignature);
@@ -213,7 +409,7 @@ export class SolanaService {
   /**
    * Complete a module (requires all challenges in the module to be completed)
    * @param wallet - The wallet adapter instance
-   * @param moduleId - The ID of the module to complete (0-3)
+   * @param moduleId - The ID of the module to complete (0-7)
    * @returns Transaction signature
    */
   async completeModule(wallet: WalletAdapter, moduleId: number): Promise<string> {
@@ -221,19 +417,43 @@ export class SolanaService {
       throw new Error('Wallet not connected');
     }
 
-    if (moduleId < 0 || moduleId > 3) {
-      throw new Error('Invalid module ID. Must be between 0 and 3.');
+    if (moduleId < 0 || moduleId >= MAX_MODULES) {
+      throw new Error(`Invalid module ID. Must be between 0 and ${MAX_MODULES - 1}.`);
     }
 
     const program = this.getProgram(wallet);
     const [userProgressPDA] = await this.findUserProgressPDA(wallet.publicKey);
+    const [curriculumPDA] = this.findCurriculumPDA();
 
     try {
+      // Record the completion in the learner's track progress too, if they started the track
+      const curriculum = await program.account.curriculum.fetch(curriculumPDA);
+      const trackProgress = await this.findInitializedTrackProgress(
+        program,
+        wallet.publicKey,
+        curriculum.modules.find((m) => m.moduleId === moduleId)?.trackId
+      );
+
+      // Leaderboards the admin has created must be passed, the others are skipped
+      const [globalLeaderboardPDA] = this.findLeaderboardPDA(GLOBAL_LEADERBOARD);
+      const [moduleLeaderboardPDA] = this.findLeaderboardPDA(moduleId);
+      const [globalLeaderboard, moduleLeaderboard] =
+        await program.provider.connection.getMultipleAccountsInfo([
+          globalLeaderboardPDA,
+          moduleLeaderboardPDA,
+        ]);
+
       // Build and send the complete_module transaction
       const txSignature = await program.methods
         .completeModule(moduleId)
         .accounts({
+          config: this.findConfigPDA()[0],
           userProgress: userProgressPDA,
+          curriculum: curriculumPDA,
+          trackProgress,
+          globalLeaderboard: globalLeaderboard ? globalLeaderboardPDA : null,
+          moduleLeaderboard: moduleLeaderboard ? moduleLeaderboardPDA : null,
+          roleGrant: null,
           authority: wallet.publicKey,
         })
         .rpc();
@@ -248,96 +468,95 @@ export class SolanaService {
 
   /**
    * Mint an achievement NFT for completing a module
+   * Name, symbol and URI come from the module's on-chain achievement template
    * @param wallet - The wallet adapter instance
-   * @param metadata - The NFT metadata (title, symbol, uri, moduleId)
+   * @param moduleId - The ID of the completed module (0-7)
    * @returns Transaction signature
    */
-  async mintAchievementNft(wallet: WalletAdapter, metadata: NFTMetadata): Promise<string> {
+  async mintAchievementNft(wallet: WalletAdapter, moduleId: number): Promise<string> {
     if (!wallet.publicKey) {
       throw new Error('Wallet not connected');
     }
 
-    if (metadata.moduleId < 0 || metadata.moduleId > 3) {
-      throw new Error('Invalid module ID. Must be between 0 and 3.');
+    if (moduleId < 0 || moduleId >= MAX_MODULES) {
+      throw new Error(`Invalid module ID. Must be between 0 and ${MAX_MODULES - 1}.`);
     }
 
     const program = this.getProgram(wallet);
     const [userProgressPDA] = await this.findUserProgressPDA(wallet.publicKey);
+    const [configPDA] = this.findConfigPDA();
+    const [curriculumPDA] = this.findCurriculumPDA();
 
-    // Generate a new keypair for the mint
-    const mintKeypair = Keypair.generate();
+    // The mint is a PDA of the learner and module, so each achievement can only be minted once
+    const [mint] = this.findAchievementMintPDA(wallet.publicKey, moduleId);
 
     try {
-      // Derive the metadata PDA
-      const [metadataPDA] = PublicKey.findProgramAddressSync(
-        [
-          Buffer.from('metadata'),
-          TOKEN_METADATA_PROGRAM_ID.toBuffer(),
-          mintKeypair.publicKey.toBuffer(),
-        ],
-        TOKEN_METADATA_PROGRAM_ID
-      );
-
-      // Derive the master edition PDA
-      const [masterEditionPDA] = PublicKey.findProgramAddressSync(
-        [
-          Buffer.from('metadata'),
-          TOKEN_METADATA_PROGRAM_ID.toBuffer(),
-          mintKeypair.publicKey.toBuffer(),
-          Buffer.from('edition'),
-        ],
-        TOKEN_METADATA_PROGRAM_ID
+      // The fee goes to the treasury, and the NFT joins the collection of the module's track
+      const config = await program.account.programConfig.fetch(configPDA);
+      const curriculum = await program.account.curriculum.fetch(curriculumPDA);
+      const trackId = curriculum.modules.find((m) => m.moduleId === moduleId)?.trackId ?? 0;
+      const [collectionMint] = PublicKey.findProgramAddressSync(
+        [Buffer.from(COLLECTION_MINT_SEED), Buffer.from([trackId])],
+        this.programId
       );
 
       // Get the associated token account for the user
-      const userTokenAccount = await getAssociatedTokenAddress(
-        mintKeypair.publicKey,
-        wallet.publicKey
-      );
+      const userTokenAccount = await getAssociatedTokenAddress(mint, wallet.publicKey);
 
       // Build and send the mint_achievement_nft transaction
       const txSignature = await program.methods
-        .mintAchievementNft(
-          metadata.title,
-          metadata.symbol,
-          metadata.uri,
-          metadata.moduleId
-        )
+        .mintAchievementNft(moduleId)
         .accounts({
+          config: configPDA,
           userProgress: userProgressPDA,
-          mint: mintKeypair.publicKey,
-          metadata: metadataPDA,
-          masterEdition: masterEditionPDA,
-          userTokenAccount: userTokenAccount,
+          achievementAuthority: PublicKey.findProgramAddressSync(
+            [Buffer.from(ACHIEVEMENT_AUTHORITY_SEED)],
+            this.programId
+          )[0],
+          mint,
+          userTokenAccount,
+          metadata: this.findMetadataPDA(mint)[0],
+          masterEdition: this.findMetadataPDA(mint, true)[0],
+          curriculum: curriculumPDA,
+          template: PublicKey.findProgramAddressSync(
+            [Buffer.from(ACHIEVEMENT_TEMPLATE_SEED), Buffer.from([moduleId])],
+            this.programId
+          )[0],
+          collectionMint,
+          collectionMetadata: this.findMetadataPDA(collectionMint)[0],
+          collectionMasterEdition: this.findMetadataPDA(collectionMint, true)[0],
           payer: wallet.publicKey,
+          sponsor: null,
+          roleGrant: null,
           authority: wallet.publicKey,
+          learner: wallet.publicKey,
+          treasury: config.treasury,
           systemProgram: SystemProgram.programId,
           tokenProgram: TOKEN_PROGRAM_ID,
           associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          metadataProgram: TOKEN_METADATA_PROGRAM_ID,
           rent: SYSVAR_RENT_PUBKEY,
         })
-        .signers([mintKeypair])
         .rpc();
 
-      console.log(`Achievement NFT minted successfully for module ${metadata.moduleId}:`, txSignature);
-      console.log('Mint address:', mintKeypair.publicKey.toString());
+      console.log(`Achievement NFT minted successfully for module ${moduleId}:`, txSignature);
+      console.log('Mint address:', mint.toString());
       
       return txSignature;
     } catch (error) {
-      console.error(`Failed to mint achievement NFT for module ${metadata.moduleId}:`, error);
+      console.error(`Failed to mint achievement NFT for module ${moduleId}:`, error);
       throw new Error(`Failed to mint achievement NFT: ${error}`);
     }
   }
 
   /**
    * Check if a specific challenge is completed
-   * @param challengesCompleted - The challenges completed bitmask
+   * @param challengesCompleted - The challenges completed bitmap (32 bytes, 256 challenges)
    * @param challengeId - The challenge ID to check
    * @returns True if the challenge is completed
    */
-  isChallengeCompleted(challengesCompleted: number, challengeId: number): boolean {
-    return (challengesCompleted & (1 << challengeId)) !== 0;
+  isChallengeCompleted(challengesCompleted: number[], challengeId: number): boolean {
+    return (challengesCompleted[challengeId >> 3] & (1 << (challengeId & 7))) !== 0;
   }
 
   /**
@@ -351,13 +570,13 @@ export class SolanaService {
   }
 
   /**
-   * Get all completed challenges from the bitmask
-   * @param challengesCompleted - The challenges completed bitmask
+   * Get all completed challenges from the bitmap
+   * @param challengesCompleted - The challenges completed bitmap
    * @returns Array of completed challenge IDs
    */
-  getCompletedChallenges(challengesCompleted: number): number[] {
+  getCompletedChallenges(challengesCompleted: number[]): number[] {
     const completed: number[] = [];
-    for (let i = 0; i < 16; i++) {
+    for (let i = 0; i < challengesCompleted.length * 8; i++) {
       if (this.isChallengeCompleted(challengesCompleted, i)) {
         completed.push(i);
       }
@@ -372,7 +591,7 @@ export class SolanaService {
    */
   getCompletedModules(modulesCompleted: number): number[] {
     const completed: number[] = [];
-    for (let i = 0; i < 4; i++) {
+    for (let i = 0; i < MAX_MODULES; i++) {
       if (this.isModuleCompleted(modulesCompleted, i)) {
         completed.push(i);
       }
@@ -12,8 +12,7 @@ import { useWallet } from "@/hooks/use-wallet";
 import { 
   SolanaService, 
   createConnection, 
-  UserProgress,
-  NFTMetadata 
+  UserProgress
 } from "@/lib/solanaService";
 import { Connection, PublicKey } from "@solana/web3.js";
 
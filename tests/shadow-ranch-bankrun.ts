@@ -0,0 +1,627 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, Wallet } from "@coral-xyz/anchor";
+import { BankrunProvider } from "anchor-bankrun";
+import { start, Clock, ProgramTestContext } from "solana-bankrun";
+import { IDL, ShadowRanchProgram } from "../target/types/shadow_ranch_program";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  Ed25519Program,
+  LAMPORTS_PER_SOL,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Transaction,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import * as fs from "fs";
+
+// Tests that write accounts directly or warp the clock run against a bankrun bank, with Token
+// Metadata loaded from tests/fixtures, which `yarn anchor:fixtures` dumps before the tests run
+describe("shadow-ranch-program (bankrun)", () => {
+  const PROGRAM_ID = new PublicKey("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+  const BPF_LOADER_UPGRADEABLE_ID = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+  const TOKEN_METADATA_PROGRAM_ID = new PublicKey(
+    "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+  );
+
+  // Upgrade authority of the program, and so the only key that can initialize the config
+  const admin = Keypair.generate();
+
+  let context: ProgramTestContext;
+  let provider: BankrunProvider;
+  let program: Program<ShadowRanchProgram>;
+
+  const pda = (...seeds: Buffer[]) => PublicKey.findProgramAddressSync(seeds, PROGRAM_ID)[0];
+
+  const userProgressPdaFor = (authority: PublicKey) =>
+    pda(Buffer.from("user_progress"), authority.toBuffer());
+
+  const tombstonePdaFor = (authority: PublicKey) =>
+    pda(Buffer.from("user_tombstone"), authority.toBuffer());
+
+  const configPda = pda(Buffer.from("config"));
+  const curriculumPda = pda(Buffer.from("curriculum"));
+  const ranchCoinMint = pda(Buffer.from("ranch_coin_mint"));
+  const rewardConfigPda = pda(Buffer.from("reward_config"));
+
+  // Role discriminant of `Role::Verifier`
+  const VERIFIER = 1;
+
+  // Verifier grants aren't scoped to a track, so they live under track 0
+  const roleGrantPdaFor = (role: number, grantee: PublicKey) =>
+    pda(Buffer.from("role_grant"), Buffer.from([role]), Buffer.from([0]), grantee.toBuffer());
+
+  const [programDataPda] = PublicKey.findProgramAddressSync(
+    [PROGRAM_ID.toBuffer()],
+    BPF_LOADER_UPGRADEABLE_ID
+  );
+
+  // Deploys the program under the upgradeable loader, so `initialize_config` finds its ProgramData
+  const upgradeableProgramAccounts = () => {
+    const elf = fs.readFileSync("target/deploy/shadow_ranch_program.so");
+
+    // UpgradeableLoaderState::Program { programdata_address }
+    const programAccount = Buffer.alloc(36);
+    programAccount.writeUInt32LE(2, 0);
+    programDataPda.toBuffer().copy(programAccount, 4);
+
+    // UpgradeableLoaderState::ProgramData { slot, upgrade_authority_address } followed by the ELF
+    const programDataHeader = Buffer.alloc(45);
+    programDataHeader.writeUInt32LE(3, 0);
+    programDataHeader.writeBigUInt64LE(BigInt(0), 4);
+    programDataHeader.writeUInt8(1, 12);
+    admin.publicKey.toBuffer().copy(programDataHeader, 13);
+
+    return [
+      {
+        address: PROGRAM_ID,
+        info: {
+          lamports: LAMPORTS_PER_SOL,
+          data: programAccount,
+          owner: BPF_LOADER_UPGRADEABLE_ID,
+          executable: true,
+        },
+      },
+      {
+        address: programDataPda,
+        info: {
+          lamports: 100 * LAMPORTS_PER_SOL,
+          data: Buffer.concat([programDataHeader, elf]),
+          owner: BPF_LOADER_UPGRADEABLE_ID,
+          executable: false,
+        },
+      },
+      {
+        address: admin.publicKey,
+        info: {
+          lamports: 1_000 * LAMPORTS_PER_SOL,
+          data: Buffer.alloc(0),
+          owner: SystemProgram.programId,
+          executable: false,
+        },
+      },
+    ];
+  };
+
+  const now = async () => Number((await context.banksClient.getClock()).unixTimestamp);
+
+  const fund = async (publicKey: PublicKey) => {
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: admin.publicKey,
+          toPubkey: publicKey,
+          lamports: 2 * LAMPORTS_PER_SOL,
+        })
+      )
+    );
+  };
+
+  // Processes a transaction paid for by the first signer, returning its error (if any) and logs
+  const processTransaction = async (tx: Transaction, signers: Keypair[]) => {
+    [tx.recentBlockhash] = await context.banksClient.getLatestBlockhash();
+    tx.feePayer = signers[0].publicKey;
+    tx.sign(...signers);
+
+    const { result, meta } = await context.banksClient.tryProcessTransaction(tx);
+    return { result, logs: meta.logMessages };
+  };
+
+  const failureLogsOf = async (tx: Transaction, signers: Keypair[]) => {
+    const { result, logs } = await processTransaction(tx, signers);
+    expect(result, "the transaction should fail").to.not.be.null;
+    return logs.join("\n");
+  };
+
+  // Mirrors `challenge_attestation_message` in the program
+  const attestationMessage = (learner: PublicKey, challengeId: number, expiry: number) =>
+    Buffer.concat([
+      Buffer.from("shadow-ranch:challenge-attestation:v1"),
+      PROGRAM_ID.toBuffer(),
+      learner.toBuffer(),
+      Buffer.from([challengeId]),
+      new anchor.BN(expiry).toArrayLike(Buffer, "le", 8),
+    ]);
+
+  const verifier = Keypair.generate();
+
+  const initializeLearner = async (learner: Keypair) => {
+    await fund(learner.publicKey);
+    await program.methods
+      .initializeUser()
+      .accounts({
+        config: configPda,
+        userProgress: userProgressPdaFor(learner.publicKey),
+        tombstone: tombstonePdaFor(learner.publicKey),
+        payer: learner.publicKey,
+        sponsor: null,
+        authority: learner.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([learner])
+      .rpc();
+  };
+
+  const completeChallenge = async (learner: Keypair, challengeId: number) => {
+    // Attestations expire against the bank's clock, which tests may have warped
+    const expiry = (await now()) + 600;
+    const attestation = Ed25519Program.createInstructionWithPrivateKey({
+      privateKey: verifier.secretKey,
+      message: attestationMessage(learner.publicKey, challengeId, expiry),
+    });
+
+    const tx = await program.methods
+      .completeChallenge(challengeId, new anchor.BN(expiry))
+      .accounts({
+        config: configPda,
+        userProgress: userProgressPdaFor(learner.publicKey),
+        verifier: roleGrantPdaFor(VERIFIER, verifier.publicKey),
+        curriculum: curriculumPda,
+        trackProgress: null,
+        authority: learner.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([attestation])
+      .transaction();
+
+    // The logs are returned so callers can decode the emitted events
+    const { result, logs } = await processTransaction(tx, [learner]);
+    expect(result, logs.join("\n")).to.be.null;
+    return logs;
+  };
+
+  const completeModule = (learner: Keypair, moduleId: number) =>
+    program.methods
+      .completeModule(moduleId)
+      .accounts({
+        config: configPda,
+        userProgress: userProgressPdaFor(learner.publicKey),
+        curriculum: curriculumPda,
+        trackProgress: null,
+        globalLeaderboard: null,
+        moduleLeaderboard: null,
+        roleGrant: null,
+        authority: learner.publicKey,
+      })
+      .signers([learner])
+      .rpc();
+
+  // Module 0 (challenges 0-2) and a verifier, shared by every test below
+  before(async () => {
+    context = await start(
+      [{ name: "mpl_token_metadata", programId: TOKEN_METADATA_PROGRAM_ID }],
+      upgradeableProgramAccounts()
+    );
+
+    // Programs deployed at slot 0 only become visible from the next slot
+    context.warpToSlot(BigInt(2));
+
+    provider = new BankrunProvider(context, new Wallet(admin));
+    program = new Program<ShadowRanchProgram>(IDL, PROGRAM_ID, provider);
+
+    await program.methods
+      .initializeConfig(new anchor.BN(0), admin.publicKey)
+      .accounts({
+        config: configPda,
+        admin: admin.publicKey,
+        program: PROGRAM_ID,
+        programData: programDataPda,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .initializeCurriculum()
+      .accounts({
+        config: configPda,
+        curriculum: curriculumPda,
+        admin: admin.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .createModule({
+        moduleId: 0,
+        trackId: 0,
+        challengeIds: Buffer.from([0, 1, 2]),
+        prerequisites: Buffer.from([]),
+        challengeXp: 10,
+        moduleXp: 50,
+        title: "Ranch Foundations",
+        uri: "https://shadowranch.xyz/modules/0.json",
+      })
+      .accounts({
+        config: configPda,
+        curriculum: curriculumPda,
+        roleGrant: null,
+        authority: admin.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .grantRole({ verifier: {} }, verifier.publicKey, 0)
+      .accounts({
+        config: configPda,
+        roleGrant: roleGrantPdaFor(VERIFIER, verifier.publicKey),
+        admin: admin.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  describe("achievement NFTs", () => {
+    const learner = Keypair.generate();
+    const moduleId = 0;
+    const trackId = 0;
+
+    const metadataPdaFor = (mint: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), mint.toBuffer()],
+        TOKEN_METADATA_PROGRAM_ID
+      )[0];
+
+    const masterEditionPdaFor = (mint: PublicKey) =>
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("metadata"),
+          TOKEN_METADATA_PROGRAM_ID.toBuffer(),
+          mint.toBuffer(),
+          Buffer.from("edition"),
+        ],
+        TOKEN_METADATA_PROGRAM_ID
+      )[0];
+
+    const achievementAuthority = pda(Buffer.from("achievement_authority"));
+    const mint = pda(
+      Buffer.from("achievement_mint"),
+      learner.publicKey.toBuffer(),
+      Buffer.from([moduleId])
+    );
+    const metadata = metadataPdaFor(mint);
+    const masterEdition = masterEditionPdaFor(mint);
+    const userTokenAccount = anchor.utils.token.associatedAddress({
+      mint,
+      owner: learner.publicKey,
+    });
+    const collectionMint = pda(Buffer.from("collection_mint"), Buffer.from([trackId]));
+    const template = pda(Buffer.from("achievement_template"), Buffer.from([moduleId]));
+
+    // SPL Token account layout: mint (32) | owner (32) | amount (8) | delegate option (36) | state (1)
+    const tokenAccountOf = async (address: PublicKey) => {
+      const account = await context.banksClient.getAccount(address);
+      const data = Buffer.from(account.data);
+      return {
+        amount: data.readBigUInt64LE(64),
+        delegate: data.readUInt32LE(72) === 1 ? new PublicKey(data.subarray(76, 108)) : null,
+        frozen: data[108] === 2,
+      };
+    };
+
+    before(async () => {
+      await program.methods
+        .createAchievementCollection(
+          trackId,
+          "Solana Corps of Engineers",
+          "SRCORPS",
+          "https://shadowranch.xyz/nft/collections/0.json"
+        )
+        .accounts({
+          config: configPda,
+          achievementAuthority,
+          collectionMint,
+          collectionTokenAccount: anchor.utils.token.associatedAddress({
+            mint: collectionMint,
+            owner: achievementAuthority,
+          }),
+          collectionMetadata: metadataPdaFor(collectionMint),
+          collectionMasterEdition: masterEditionPdaFor(collectionMint),
+          admin: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        ])
+        .rpc();
+
+      await program.methods
+        .createAchievementTemplate(moduleId, {
+          name: "Ranch Foundations",
+          symbol: "SRANCH",
+          uri: "https://shadowranch.xyz/nft/0.json",
+          attributes: [{ traitType: "Track", value: "Solana Corps of Engineers" }],
+          soulbound: true,
+        })
+        .accounts({
+          config: configPda,
+          template,
+          admin: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await initializeLearner(learner);
+
+      for (const challengeId of [0, 1, 2]) {
+        await completeChallenge(learner, challengeId);
+      }
+      await completeModule(learner, moduleId);
+    });
+
+    it("Mints the achievement NFT into the learner's ATA", async () => {
+      await program.methods
+        .mintAchievementNft(moduleId)
+        .accounts({
+          config: configPda,
+          userProgress: userProgressPdaFor(learner.publicKey),
+          achievementAuthority,
+          mint,
+          userTokenAccount,
+          metadata,
+          masterEdition,
+          curriculum: curriculumPda,
+          template,
+          collectionMint,
+          collectionMetadata: metadataPdaFor(collectionMint),
+          collectionMasterEdition: masterEditionPdaFor(collectionMint),
+          payer: learner.publicKey,
+          sponsor: null,
+          roleGrant: null,
+          authority: learner.publicKey,
+          learner: learner.publicKey,
+          treasury: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+          associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+          metadataProgram: TOKEN_METADATA_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: 400_000 }),
+        ])
+        .signers([learner])
+        .rpc();
+
+      const tokenAccount = await tokenAccountOf(userTokenAccount);
+      expect(tokenAccount.amount).to.equal(BigInt(1));
+
+      const metadataAccount = await context.banksClient.getAccount(metadata);
+      const editionAccount = await context.banksClient.getAccount(masterEdition);
+      expect(metadataAccount.owner.toBase58()).to.equal(TOKEN_METADATA_PROGRAM_ID.toBase58());
+      expect(editionAccount.owner.toBase58()).to.equal(TOKEN_METADATA_PROGRAM_ID.toBase58());
+
+      // Metadata layout: key (1 byte) | update_authority (32 bytes) | mint (32 bytes) | ...
+      const updateAuthority = new PublicKey(metadataAccount.data.subarray(1, 33));
+      expect(updateAuthority.toBase58()).to.equal(achievementAuthority.toBase58());
+
+      const progress = await program.account.userProgress.fetch(
+        userProgressPdaFor(learner.publicKey)
+      );
+      expect(progress.mintedModules).to.equal(1 << moduleId);
+    });
+
+    it("Freezes soulbound achievements so they can't be transferred", async () => {
+      const tokenAccount = await tokenAccountOf(userTokenAccount);
+      expect(tokenAccount.frozen).to.be.true;
+      expect(tokenAccount.delegate.toBase58()).to.equal(achievementAuthority.toBase58());
+
+      // Create a recipient ATA and try an SPL Token `Transfer` (instruction 3) into it
+      const recipient = Keypair.generate();
+      const recipientTokenAccount = anchor.utils.token.associatedAddress({
+        mint,
+        owner: recipient.publicKey,
+      });
+      const createRecipientAta = new anchor.web3.TransactionInstruction({
+        programId: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+        keys: [
+          { pubkey: learner.publicKey, isSigner: true, isWritable: true },
+          { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: recipient.publicKey, isSigner: false, isWritable: false },
+          { pubkey: mint, isSigner: false, isWritable: false },
+          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          { pubkey: anchor.utils.token.TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        ],
+        data: Buffer.alloc(0),
+      });
+      const transfer = new anchor.web3.TransactionInstruction({
+        programId: anchor.utils.token.TOKEN_PROGRAM_ID,
+        keys: [
+          { pubkey: userTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: recipientTokenAccount, isSigner: false, isWritable: true },
+          { pubkey: learner.publicKey, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([3]), new anchor.BN(1).toArrayLike(Buffer, "le", 8)]),
+      });
+
+      // SPL Token `AccountFrozen` error
+      const logs = await failureLogsOf(new Transaction().add(createRecipientAta, transfer), [
+        learner,
+      ]);
+      expect(logs).to.include("Account is frozen");
+
+      expect((await tokenAccountOf(userTokenAccount)).amount).to.equal(BigInt(1));
+    });
+
+    it("Rejects template metadata beyond Metaplex limits", async () => {
+      const tx = await program.methods
+        .updateAchievementTemplate(moduleId, {
+          name: "A name that is far too long for Metaplex",
+          symbol: "SRANCH",
+          uri: "https://shadowranch.xyz/nft/0.json",
+          attributes: [],
+          soulbound: true,
+        })
+        .accounts({
+          config: configPda,
+          template,
+          admin: admin.publicKey,
+        })
+        .transaction();
+
+      expect(await failureLogsOf(tx, [admin])).to.include("NameTooLong");
+    });
+  });
+
+  // Warps the clock forward, so this runs after every test that doesn't expect it to move
+  describe("streak milestones", () => {
+    const learner = Keypair.generate();
+    const learnerTokenAccount = anchor.utils.token.associatedAddress({
+      mint: ranchCoinMint,
+      owner: learner.publicKey,
+    });
+    const eventParser = new anchor.EventParser(PROGRAM_ID, new anchor.BorshCoder(IDL));
+
+    const setStreakBonuses = (bonuses: number[]) =>
+      program.methods
+        .setStreakBonuses(bonuses.map((bonus) => new anchor.BN(bonus)))
+        .accounts({
+          config: configPda,
+          rewardConfig: rewardConfigPda,
+          admin: admin.publicKey,
+        })
+        .rpc();
+
+    const claimStreakBonus = () =>
+      program.methods.claimStreakBonus().accounts({
+        config: configPda,
+        userProgress: userProgressPdaFor(learner.publicKey),
+        rewardConfig: rewardConfigPda,
+        ranchCoinMint,
+        learnerTokenAccount,
+        payer: learner.publicKey,
+        sponsor: null,
+        authority: learner.publicKey,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        associatedTokenProgram: anchor.utils.token.ASSOCIATED_PROGRAM_ID,
+      });
+
+    const warpTo = async (unixTimestamp: number) => {
+      const clock = await context.banksClient.getClock();
+      context.setClock(
+        new Clock(
+          clock.slot,
+          clock.epochStartTimestamp,
+          clock.epoch,
+          clock.leaderScheduleEpoch,
+          BigInt(unixTimestamp)
+        )
+      );
+    };
+
+    let milestoneLogs: string[];
+
+    before(async () => {
+      await program.methods
+        .initializeRanchCoin(new anchor.BN(10))
+        .accounts({
+          config: configPda,
+          ranchCoinMint,
+          rewardConfig: rewardConfigPda,
+          admin: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: anchor.utils.token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      // The 7 day milestone has no bonus yet
+      await setStreakBonuses([0, 200, 1000]);
+
+      // One challenge for each day of the week
+      await program.methods
+        .createModule({
+          moduleId: 1,
+          trackId: 0,
+          challengeIds: Buffer.from([3, 4, 5, 6, 7, 8, 9]),
+          prerequisites: Buffer.from([]),
+          challengeXp: 10,
+          moduleXp: 50,
+          title: "Fencing the Frontier",
+          uri: "https://shadowranch.xyz/modules/1.json",
+        })
+        .accounts({
+          config: configPda,
+          curriculum: curriculumPda,
+          roleGrant: null,
+          authority: admin.publicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+
+      await initializeLearner(learner);
+
+      // An hour into each of the next 7 days
+      const firstDay = Math.floor((await now()) / 86_400) + 1;
+      for (let day = 0; day < 7; day++) {
+        await warpTo((firstDay + day) * 86_400 + 3_600);
+        milestoneLogs = await completeChallenge(learner, 3 + day);
+      }
+    });
+
+    it("Emits StreakMilestone when the streak reaches 7 days", async () => {
+      const progress = await program.account.userProgress.fetch(
+        userProgressPdaFor(learner.publicKey)
+      );
+      expect(progress.currentStreak).to.equal(7);
+      expect(progress.longestStreak).to.equal(7);
+
+      const events = Array.from(eventParser.parseLogs(milestoneLogs));
+      const milestone = events.find((event) => event.name === "StreakMilestone");
+      expect(milestone.data.authority.equals(learner.publicKey)).to.be.true;
+      expect(milestone.data.streak).to.equal(7);
+    });
+
+    it("Leaves a milestone unclaimed while its bonus is 0", async () => {
+      const tx = await claimStreakBonus().transaction();
+      expect(await failureLogsOf(tx, [learner])).to.include("NoStreakBonus");
+
+      const progress = await program.account.userProgress.fetch(
+        userProgressPdaFor(learner.publicKey)
+      );
+      expect(progress.streakBonusesClaimed).to.equal(0);
+    });
+
+    it("Pays the bonus once it is set", async () => {
+      await setStreakBonuses([50, 200, 1000]);
+      await claimStreakBonus().signers([learner]).rpc();
+
+      // SPL Token account layout: mint (32) | owner (32) | amount (8) | ...
+      const tokenAccount = await context.banksClient.getAccount(learnerTokenAccount);
+      expect(Buffer.from(tokenAccount.data).readBigUInt64LE(64)).to.equal(BigInt(50));
+
+      const progress = await program.account.userProgress.fetch(
+        userProgressPdaFor(learner.publicKey)
+      );
+      expect(progress.streakBonusesClaimed).to.equal(0b1);
+
+      // Paid out only once
+      const tx = await claimStreakBonus().transaction();
+      expect(await failureLogsOf(tx, [learner])).to.include("NoStreakBonus");
+    });
+  });
+});